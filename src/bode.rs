@@ -0,0 +1,235 @@
+use ndarray::{arr1, arr2};
+
+use crate::cairo_plotter::{Circle, Line, LineCap, PlotItem, Text};
+use crate::chart::{Axis, Chart, Trace};
+use crate::error::Error;
+use crate::simulation::format_si;
+use crate::text;
+use crate::theme::Theme;
+
+const MARKER_COLOR: (f64, f64, f64, f64) = (0.8, 0.0, 0.0, 1.0);
+const MARKER_RADIUS: f64 = 0.6;
+
+/// A Bode plot with the magnitude and the phase over the frequency.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bode {
+    pub title: String,
+    pub frequency: Vec<f64>,
+    /// the magnitude in dB.
+    pub magnitude: Vec<f64>,
+    /// the phase in degrees.
+    pub phase: Vec<f64>,
+}
+
+impl Bode {
+    pub fn new(title: &str, frequency: Vec<f64>, magnitude: Vec<f64>, phase: Vec<f64>) -> Self {
+        Self {
+            title: title.to_string(),
+            frequency,
+            magnitude,
+            phase,
+        }
+    }
+    /// create the Bode plot from the complex AC result.
+    ///
+    /// The phase is unwrapped, jumps of more than 180° are removed.
+    pub fn from_complex(title: &str, frequency: Vec<f64>, values: &[(f64, f64)]) -> Self {
+        let magnitude = values
+            .iter()
+            .map(|(re, im)| 20.0 * (re * re + im * im).sqrt().log10())
+            .collect();
+        let mut phase: Vec<f64> = Vec::new();
+        for (re, im) in values {
+            let mut value = im.atan2(*re).to_degrees();
+            if let Some(last) = phase.last() {
+                value += ((last - value) / 360.0).round() * 360.0;
+            }
+            phase.push(value);
+        }
+        Self::new(title, frequency, magnitude, phase)
+    }
+    /// get the -3 dB frequency, relative to the magnitude at the first frequency.
+    pub fn cutoff(&self) -> Option<f64> {
+        let reference = *self.magnitude.first()?;
+        crossing(&self.frequency, &self.magnitude, reference - 3.0)
+    }
+    /// get the unity gain frequency.
+    pub fn unity_gain(&self) -> Option<f64> {
+        crossing(&self.frequency, &self.magnitude, 0.0)
+    }
+    /// get the phase margin in degrees at the unity gain frequency.
+    pub fn phase_margin(&self) -> Option<f64> {
+        let frequency = self.unity_gain()?;
+        Some(180.0 + interpolate(&self.frequency, &self.phase, frequency)?)
+    }
+    /// plot the magnitude above the phase into the area of the page.
+    pub fn plot(
+        &self,
+        area: ((f64, f64), (f64, f64)),
+        theme: &Theme,
+    ) -> Result<Vec<PlotItem>, Error> {
+        let middle = (area.0 .1 + area.1 .1) / 2.0;
+        let top = (area.0, (area.1 .0, middle));
+        let bottom = ((area.0 .0, middle), area.1);
+        let mut frequency = Axis::new("", "Hz");
+        frequency.log = true;
+        let mut magnitude =
+            Chart::new(&self.title, frequency.clone(), Axis::new("magnitude", "dB"));
+        magnitude.traces.push(Trace::new(
+            "magnitude",
+            self.frequency.clone(),
+            self.magnitude.clone(),
+        ));
+        frequency.label = String::from("frequency");
+        let mut phase = Chart::new("", frequency, Axis::new("phase", "°"));
+        phase.traces.push(Trace::new(
+            "phase",
+            self.frequency.clone(),
+            self.phase.clone(),
+        ));
+        // link the frequency axes.
+        let range = (
+            magnitude.range(&magnitude.x, true),
+            phase.range(&phase.x, true),
+        );
+        let range = Some((range.0 .0.min(range.1 .0), range.0 .1.max(range.1 .1)));
+        magnitude.x.range = range;
+        phase.x.range = range;
+
+        let mut items = magnitude.plot(top, theme)?;
+        items.append(&mut phase.plot(bottom, theme)?);
+
+        let mut effects = theme.effects("border_effects")?;
+        effects.color = MARKER_COLOR;
+        effects.justify = vec![String::from("left"), String::from("bottom")];
+        if let Some(f) = self.cutoff() {
+            let db = interpolate(&self.frequency, &self.magnitude, f).unwrap_or_default();
+            let (x, y) = magnitude.map(top, f, db);
+            items.push(marker(x, y));
+            items.push(text!(
+                arr1(&[x + 1.0, y - 1.0]),
+                0.0,
+                format!("-3dB {}", format_si(f, "Hz", 3)),
+                effects
+            ));
+        }
+        if let Some(f) = self.unity_gain() {
+            let (x, y) = magnitude.map(top, f, 0.0);
+            let (_, end) = phase.map(bottom, f, phase.range(&phase.y, false).0);
+            items.push(PlotItem::Line(
+                10,
+                Line::new(
+                    arr2(&[[x, y], [x, end]]),
+                    0.15,
+                    String::from("dash"),
+                    LineCap::Butt,
+                    MARKER_COLOR,
+                ),
+            ));
+            items.push(marker(x, y));
+            items.push(text!(
+                arr1(&[x + 1.0, y - 1.0]),
+                0.0,
+                format!("0dB {}", format_si(f, "Hz", 3)),
+                effects
+            ));
+            if let Some(margin) = self.phase_margin() {
+                let (x, y) = phase.map(bottom, f, margin - 180.0);
+                items.push(marker(x, y));
+                items.push(text!(
+                    arr1(&[x + 1.0, y - 1.0]),
+                    0.0,
+                    format!("PM {:.1}°", margin),
+                    effects
+                ));
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn marker(x: f64, y: f64) -> PlotItem {
+    PlotItem::Circle(
+        99,
+        Circle::new(
+            arr1(&[x, y]),
+            MARKER_RADIUS,
+            0.15,
+            String::from("default"),
+            MARKER_COLOR,
+            None,
+        ),
+    )
+}
+
+/// get the first frequency where the values cross the level, interpolated on the log scale.
+fn crossing(frequency: &[f64], values: &[f64], level: f64) -> Option<f64> {
+    for i in 1..frequency.len().min(values.len()) {
+        let (a, b) = (values[i - 1] - level, values[i] - level);
+        if a == 0.0 {
+            return Some(frequency[i - 1]);
+        }
+        if a * b <= 0.0 && frequency[i - 1] > 0.0 && frequency[i] > 0.0 {
+            let t = a / (a - b);
+            let (f0, f1) = (frequency[i - 1].log10(), frequency[i].log10());
+            return Some(10f64.powf(f0 + t * (f1 - f0)));
+        }
+    }
+    None
+}
+
+/// get the value at the frequency, interpolated on the log scale.
+fn interpolate(frequency: &[f64], values: &[f64], f: f64) -> Option<f64> {
+    for i in 1..frequency.len().min(values.len()) {
+        if f >= frequency[i - 1] && f <= frequency[i] {
+            let (f0, f1) = (frequency[i - 1].log10(), frequency[i].log10());
+            let t = if f1 > f0 {
+                (f.log10() - f0) / (f1 - f0)
+            } else {
+                0.0
+            };
+            return Some(values[i - 1] + t * (values[i] - values[i - 1]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crossing, Bode};
+
+    #[test]
+    fn crossings() {
+        let f = crossing(&[10.0, 100.0, 1000.0], &[0.0, -2.0, -4.0], -3.0).unwrap();
+        assert!((f - 316.227766).abs() < 1e-3);
+        assert_eq!(None, crossing(&[10.0, 100.0], &[0.0, -1.0], -3.0));
+    }
+    #[test]
+    fn margins() {
+        let bode = Bode::new(
+            "amplifier",
+            vec![1.0, 10.0, 100.0],
+            vec![20.0, 0.0, -20.0],
+            vec![-90.0, -135.0, -170.0],
+        );
+        assert_eq!(Some(10.0), bode.unity_gain());
+        assert_eq!(Some(45.0), bode.phase_margin());
+        let cutoff = bode.cutoff().unwrap();
+        assert!(cutoff > 1.0 && cutoff < 10.0);
+    }
+    #[test]
+    fn complex() {
+        // first order low pass with the corner at 1kHz.
+        let frequency = vec![10.0, 1000.0, 100000.0];
+        let values: Vec<(f64, f64)> = frequency
+            .iter()
+            .map(|f| {
+                let x = f / 1000.0;
+                (1.0 / (1.0 + x * x), -x / (1.0 + x * x))
+            })
+            .collect();
+        let bode = Bode::from_complex("low pass", frequency, &values);
+        assert!((bode.magnitude[1] + 3.0103).abs() < 1e-3);
+        assert!((bode.phase[1] + 45.0).abs() < 1e-9);
+    }
+}
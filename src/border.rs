@@ -1,13 +1,115 @@
-use super::cairo_plotter::{Line, LineCap, PlotItem, Rectangle, Text};
+use super::cairo_plotter::{text_width, Image, Line, LineCap, PlotItem, Rectangle, Text};
 use super::{text, Theme};
+use crate::connections::Bounds;
 use crate::error::Error;
-use elektron_sexp::{Effects, Stroke, TitleBlock};
+use crate::variables::Variables;
+use elektron_sexp::{Effects, Schema, SchemaElement, Stroke, TitleBlock};
 use ndarray::{arr1, arr2, Array2};
 
-const BORDER_RASTER: i32 = 60;
+/// distance from the paper edge to the outer frame.
+const BORDER_MARGIN: f64 = 5.0;
+/// width of the zone band between the outer and inner frame.
+const BORDER_WIDTH: f64 = 2.0;
+/// nominal size of a zone, the zones are stretched to fill the frame.
+const ZONE_SIZE: f64 = 50.0;
+/// width of the title block.
+const HEAD_WIDTH: f64 = 115.0;
+/// height of the title block without the comments.
+const HEAD_HEIGHT: f64 = 21.0;
+/// space for the text right of the time cursor.
+const CURSOR_TEXT: f64 = 30.0;
+/// height of the time cursor bar.
+const CURSOR_HEIGHT: f64 = 2.0;
+/// height of a comment line in the title block.
+const COMMENT_HEIGHT: f64 = 4.0;
+/// KiCad supports the comments 1 to 9.
+const MAX_COMMENTS: usize = 9;
+/// height of a legend line.
+const LEGEND_LINE: f64 = 4.0;
+
+/// The position of the plotted page in the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetInfo {
+    pub number: usize,
+    pub count: usize,
+    pub name: String,
+    pub path: String,
+    pub filename: String,
+}
+
+impl Default for SheetInfo {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            count: 1,
+            name: String::new(),
+            path: String::from("/"),
+            filename: String::new(),
+        }
+    }
+}
+
+/// get the sheet information for all pages of the schema.
+///
+/// The first page is the root sheet, the following pages are the sheets of the root
+/// page in the order they are placed.
+pub fn sheets(schema: &Schema, filename: &str) -> Result<Vec<SheetInfo>, Error> {
+    let count = schema.pages();
+    let mut sheets = vec![SheetInfo {
+        count,
+        filename: filename.to_string(),
+        ..Default::default()
+    }];
+    if count > 0 {
+        for element in schema.iter(0)? {
+            if let SchemaElement::Sheet(sheet) = element {
+                let property = |keys: &[&str]| {
+                    sheet
+                        .property
+                        .iter()
+                        .find(|p| keys.contains(&p.key.as_str()))
+                        .map(|p| p.value.clone())
+                        .unwrap_or_default()
+                };
+                let name = property(&["Sheet name", "Sheetname"]);
+                sheets.push(SheetInfo {
+                    number: sheets.len() + 1,
+                    count,
+                    path: format!("/{}/", name),
+                    name,
+                    filename: property(&["Sheet file", "Sheetfile"]),
+                });
+            }
+        }
+    }
+    Ok(sheets)
+}
+
+/// get the number and the size of the zones for the length of the frame.
+fn zones(length: f64) -> (usize, f64) {
+    let count = ((length / ZONE_SIZE).round() as usize).max(1);
+    (count, length / count as f64)
+}
+
+/// get the zone letter, after `Z` the letters continue with `AA`, `AB`...
+fn zone_letter(index: usize) -> String {
+    let mut index = index;
+    let mut letter = String::new();
+    loop {
+        letter.insert(0, (b'A' + (index % 26) as u8) as char);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letter
+}
 
 pub fn draw_border(
     title_block: &Option<TitleBlock>,
+    sheet: &SheetInfo,
+    variables: &Variables,
+    logo: Option<&[u8]>,
     paper_size: (f64, f64),
     theme: &Theme,
 ) -> Result<Vec<PlotItem>, Error> {
@@ -15,36 +117,42 @@ pub fn draw_border(
     let stroke: Stroke = theme.stroke("border_stroke")?;
     let effects: Effects = theme.effects("border_effects")?;
     //outline
-    let pts: Array2<f64> = arr2(&[[5.0, 5.0], [paper_size.0 - 5.0, paper_size.1 - 5.0]]);
-    plotter.push(PlotItem::Rectangle(
-        99,
-        Rectangle::new(
-            pts,
-            stroke.color,
-            stroke.width,
-            stroke.linetype.clone(),
-            None,
-        ),
-    ));
+    for offset in &[BORDER_MARGIN, BORDER_MARGIN + BORDER_WIDTH] {
+        let pts: Array2<f64> = arr2(&[
+            [*offset, *offset],
+            [paper_size.0 - offset, paper_size.1 - offset],
+        ]);
+        plotter.push(PlotItem::Rectangle(
+            99,
+            Rectangle::new(
+                pts,
+                stroke.color,
+                stroke.width,
+                stroke.linetype.clone(),
+                None,
+            ),
+        ));
+    }
 
-    //horizontal raster
-    for j in &[(0.0_f64, 5.0_f64), (paper_size.1 - 5.0, paper_size.1)] {
-        for i in 0..(paper_size.0 as i32 / BORDER_RASTER) {
-            let pts: Array2<f64> = arr2(&[
-                [(i as f64 + 1.0) * BORDER_RASTER as f64, j.0],
-                [(i as f64 + 1.0) * BORDER_RASTER as f64, j.1],
-            ]);
-            plotter.push(PlotItem::Rectangle(
-                99,
-                Rectangle::new(pts, stroke.color, 0.1, stroke.linetype.clone(), None),
-            ));
-        }
-        for i in 0..(paper_size.0 as i32 / BORDER_RASTER + 1) {
+    //horizontal raster, numbers on the top and bottom
+    let (count, size) = zones(paper_size.0 - 2.0 * BORDER_MARGIN);
+    for band in &[BORDER_MARGIN, paper_size.1 - BORDER_MARGIN - BORDER_WIDTH] {
+        for i in 0..count {
+            let x = BORDER_MARGIN + i as f64 * size;
+            if i > 0 {
+                plotter.push(PlotItem::Line(
+                    99,
+                    Line::new(
+                        arr2(&[[x, *band], [x, band + BORDER_WIDTH]]),
+                        0.1,
+                        stroke.linetype.clone(),
+                        LineCap::Butt,
+                        stroke.color,
+                    ),
+                ));
+            }
             plotter.push(text!(
-                arr1(&[
-                    (i as f64) * BORDER_RASTER as f64 + BORDER_RASTER as f64 / 2.0,
-                    j.0 + 2.5
-                ]),
+                arr1(&[x + size / 2.0, band + BORDER_WIDTH / 2.0]),
                 0.0,
                 (i + 1).to_string(),
                 effects
@@ -52,168 +160,419 @@ pub fn draw_border(
         }
     }
 
-    //vertical raster
-    let letters = vec![
-        'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
-        's', 't', 'u', 'v', 'w', 'x', 'y', 'z',
-    ];
-    for j in &[(0.0_f64, 5.0_f64), (paper_size.0 - 5.0, paper_size.0)] {
-        for i in 0..(paper_size.1 as i32 / BORDER_RASTER) {
-            let pts: Array2<f64> = arr2(&[
-                [j.0, (i as f64 + 1.0) * BORDER_RASTER as f64],
-                [j.1, (i as f64 + 1.0) * BORDER_RASTER as f64],
-            ]);
-            plotter.push(PlotItem::Rectangle(
-                99,
-                Rectangle::new(pts, stroke.color, 0.1, stroke.linetype.clone(), None),
-            ));
-        }
-        for i in 0..(paper_size.0 as i32 / BORDER_RASTER + 1) {
+    //vertical raster, letters on the left and right
+    let (count, size) = zones(paper_size.1 - 2.0 * BORDER_MARGIN);
+    for band in &[BORDER_MARGIN, paper_size.0 - BORDER_MARGIN - BORDER_WIDTH] {
+        for i in 0..count {
+            let y = BORDER_MARGIN + i as f64 * size;
+            if i > 0 {
+                plotter.push(PlotItem::Line(
+                    99,
+                    Line::new(
+                        arr2(&[[*band, y], [band + BORDER_WIDTH, y]]),
+                        0.1,
+                        stroke.linetype.clone(),
+                        LineCap::Butt,
+                        stroke.color,
+                    ),
+                ));
+            }
             plotter.push(text!(
-                arr1(&[
-                    j.0 + 2.5,
-                    (i as f64) * BORDER_RASTER as f64 + BORDER_RASTER as f64 / 2.0
-                ]),
+                arr1(&[band + BORDER_WIDTH / 2.0, y + size / 2.0]),
                 0.0,
-                letters[i as usize].to_string(),
+                zone_letter(i),
                 effects
             ));
         }
     }
 
-    // the head
-    let pts: Array2<f64> = arr2(&[
-        [paper_size.0 - 120.0, paper_size.1 - 40.0],
-        [paper_size.0 - 5.0, paper_size.1 - 5.0],
-    ]);
+    // the head is placed in the bottom right corner of the inner frame.
+    let corner = (
+        paper_size.0 - BORDER_MARGIN - BORDER_WIDTH,
+        paper_size.1 - BORDER_MARGIN - BORDER_WIDTH,
+    );
+    let comments = if let Some(title_block) = title_block {
+        title_block
+            .comment
+            .iter()
+            .map(|(key, _)| *key as usize)
+            .filter(|key| *key <= MAX_COMMENTS)
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let height = HEAD_HEIGHT + comments as f64 * COMMENT_HEIGHT;
     plotter.push(PlotItem::Rectangle(
         99,
         Rectangle::new(
-            pts,
-            stroke.color,
-            stroke.width,
-            stroke.linetype.clone(),
-            None,
-        ),
-    ));
-    /* plotter.push(PlotItem::Line(
-        99,
-        Line::new(
             arr2(&[
-                [paper_size.0 - 120.0, paper_size.1 - 30.0],
-                [paper_size.0 - 5.0, paper_size.1 - 30.0],
+                [corner.0 - HEAD_WIDTH, corner.1 - height],
+                [corner.0, corner.1],
             ]),
-            stroke.width,
-            stroke.linetype.clone(),
-            LineCap::Butt,
             stroke.color,
-        ),
-    )); */
-    plotter.push(PlotItem::Line(
-        99,
-        Line::new(
-            arr2(&[
-                [paper_size.0 - 120.0, paper_size.1 - 10.0],
-                [paper_size.0 - 5.0, paper_size.1 - 10.0],
-            ]),
             stroke.width,
             stroke.linetype.clone(),
-            LineCap::Butt,
-            stroke.color,
+            None,
         ),
     ));
-    plotter.push(PlotItem::Line(
-        99,
-        Line::new(
-            arr2(&[
-                [paper_size.0 - 120.0, paper_size.1 - 16.0],
-                [paper_size.0 - 5.0, paper_size.1 - 16.0],
-            ]),
-            stroke.width,
-            stroke.linetype.clone(),
-            LineCap::Butt,
-            stroke.color,
-        ),
+    // row separators
+    for y in &[4.0, 8.0, 12.0, 17.0] {
+        plotter.push(PlotItem::Line(
+            99,
+            Line::new(
+                arr2(&[
+                    [corner.0 - HEAD_WIDTH, corner.1 - y],
+                    [corner.0, corner.1 - y],
+                ]),
+                stroke.width,
+                stroke.linetype.clone(),
+                LineCap::Butt,
+                stroke.color,
+            ),
+        ));
+    }
+    // column separators of the lower rows
+    for x in &[HEAD_WIDTH - 35.0, 30.0] {
+        plotter.push(PlotItem::Line(
+            99,
+            Line::new(
+                arr2(&[[corner.0 - x, corner.1 - 8.0], [corner.0 - x, corner.1]]),
+                stroke.width,
+                stroke.linetype.clone(),
+                LineCap::Butt,
+                stroke.color,
+            ),
+        ));
+    }
+
+    let left = corner.0 - HEAD_WIDTH + 2.0;
+    let middle = corner.0 - HEAD_WIDTH + 37.0;
+    let right = corner.0 - 28.0;
+    let effects: Effects = theme.effects("subtitle_effects")?;
+    let title_effects: Effects = theme.effects("title_effects")?;
+    plotter.push(text!(
+        arr1(&[left, corner.1 - 2.0]),
+        0.0,
+        variables.resolve("Size: ${PAPER}"),
+        effects
+    ));
+    plotter.push(text!(
+        arr1(&[left, corner.1 - 6.0]),
+        0.0,
+        format!("Sheet: {} of {}", sheet.number, sheet.count),
+        effects
+    ));
+    plotter.push(text!(
+        arr1(&[middle, corner.1 - 6.0]),
+        0.0,
+        format!("File: {}", sheet.filename),
+        effects
     ));
+    plotter.push(text!(
+        arr1(&[left, corner.1 - 10.0]),
+        0.0,
+        format!("Sheet: {}{}", sheet.path, sheet.name),
+        effects
+    ));
+    let version = variables.resolve("${KICAD_VERSION}");
+    if !version.is_empty() {
+        plotter.push(text!(arr1(&[right, corner.1 - 6.0]), 0.0, version, effects));
+    }
 
     if let Some(title_block) = title_block {
-        let left = paper_size.0 - 117.0;
-        let effects: Effects = theme.effects("subtitle_effects").unwrap();
-        for (key, comment) in &title_block.comment {
-            if *key == 1 {
-                plotter.push(text!(
-                    arr1(&[left, paper_size.1 - 25.0]),
-                    0.0,
-                    comment.to_string(),
-                    effects
-                ));
-            } else if *key == 2 {
-                plotter.push(text!(
-                    arr1(&[left, paper_size.1 - 29.0]),
-                    0.0,
-                    comment.to_string(),
-                    effects
-                ));
-            } else if *key == 3 {
-                plotter.push(text!(
-                    arr1(&[left, paper_size.1 - 33.0]),
-                    0.0,
-                    comment.to_string(),
-                    effects
-                ));
-            } else if *key == 4 {
-                plotter.push(text!(
-                    arr1(&[left, paper_size.1 - 37.0]),
-                    0.0,
-                    comment.to_string(),
-                    effects
-                ));
-            }
-        }
-        if !title_block.company.is_empty() {
-            let effects: Effects = theme.effects("title_effects").unwrap();
+        if !title_block.date.is_empty() {
             plotter.push(text!(
-                arr1(&[left, paper_size.1 - 21.0]),
+                arr1(&[middle, corner.1 - 2.0]),
                 0.0,
-                title_block.company.clone(),
+                format!("Date: {}", variables.resolve(&title_block.date)),
                 effects
             ));
         }
-        if !title_block.title.is_empty() {
-            let effects: Effects = theme.effects("title_effects").unwrap();
+        if !title_block.rev.is_empty() {
             plotter.push(text!(
-                arr1(&[left, paper_size.1 - 13.0]),
+                arr1(&[right, corner.1 - 2.0]),
                 0.0,
-                format!("Title: {}", title_block.title),
+                format!("Rev: {}", variables.resolve(&title_block.rev)),
                 effects
             ));
         }
-        let effects: Effects = theme.effects("title_effects").unwrap();
-        plotter.push(text!(
-            arr1(&[left, paper_size.1 - 8.0]),
-            0.0,
-            format!("Paper: {}", String::from("xxx")),
-            effects
-        ));
-
-        if !title_block.date.is_empty() {
-            let effects: Effects = theme.effects("title_effects").unwrap();
+        if !title_block.title.is_empty() {
             plotter.push(text!(
-                arr1(&[paper_size.0 - 90.0, paper_size.1 - 8.0]),
+                arr1(&[left, corner.1 - 14.5]),
                 0.0,
-                format!("Data: {}", title_block.date),
-                effects
+                format!("Title: {}", variables.resolve(&title_block.title)),
+                title_effects
             ));
         }
-        if !title_block.rev.is_empty() {
-            let effects: Effects = theme.effects("title_effects").unwrap();
+        if !title_block.company.is_empty() {
             plotter.push(text!(
-                arr1(&[paper_size.0 - 20.0, paper_size.1 - 8.0]),
+                arr1(&[left, corner.1 - 19.0]),
                 0.0,
-                format!("Rev: {}", title_block.rev),
-                effects
+                variables.resolve(&title_block.company),
+                title_effects
             ));
         }
+        // comment 1 is the lowest comment line
+        for (key, comment) in &title_block.comment {
+            let key = *key as usize;
+            if (1..=MAX_COMMENTS).contains(&key) {
+                plotter.push(text!(
+                    arr1(&[
+                        left,
+                        corner.1 - HEAD_HEIGHT - (key as f64 - 0.5) * COMMENT_HEIGHT
+                    ]),
+                    0.0,
+                    variables.resolve(&comment.to_string()),
+                    effects
+                ));
+            }
+        }
+    }
+    if let Some(logo) = logo {
+        plotter.push(draw_logo(logo, corner));
+    }
+    Ok(plotter)
+}
+
+/// A legend entry with the text and the color.
+pub type LegendEntry = (String, (f64, f64, f64, f64));
+
+/// The corner of the inner frame for a legend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    /// the bottom right corner is taken by the title block.
+    BottomLeft,
+}
+
+/// get the outline of the legend in the corner of the inner frame.
+pub fn legend_bounds(
+    entries: &[LegendEntry],
+    corner: Corner,
+    paper_size: (f64, f64),
+    theme: &Theme,
+) -> Result<Bounds, Error> {
+    let effects: Effects = theme.effects("border_effects")?;
+    let inset = BORDER_MARGIN + BORDER_WIDTH + 2.0;
+    let width = entries
+        .iter()
+        .map(|(text, _)| text_width(text, effects.font_size.0))
+        .fold(0.0, f64::max)
+        + LEGEND_LINE
+        + 2.0;
+    let height = entries.len() as f64 * LEGEND_LINE + 1.0;
+    let origin = match corner {
+        Corner::TopLeft => (inset, inset),
+        Corner::TopRight => (paper_size.0 - inset - width, inset),
+        Corner::BottomLeft => (inset, paper_size.1 - inset - height),
+    };
+    Ok((origin, (origin.0 + width, origin.1 + height)))
+}
+
+/// draw a legend in the corner of the inner frame.
+///
+/// Every entry is a text with a colored mark in front.
+pub fn draw_legend(
+    entries: &[LegendEntry],
+    corner: Corner,
+    paper_size: (f64, f64),
+    theme: &Theme,
+) -> Result<Vec<PlotItem>, Error> {
+    let mut plotter: Vec<PlotItem> = Vec::new();
+    if entries.is_empty() {
+        return Ok(plotter);
+    }
+    let stroke: Stroke = theme.stroke("border_stroke")?;
+    let mut effects: Effects = theme.effects("border_effects")?;
+    effects.justify = vec![String::from("left")];
+    let ((origin, top), (right, bottom)) = legend_bounds(entries, corner, paper_size, theme)?;
+    plotter.push(PlotItem::Rectangle(
+        99,
+        Rectangle::new(
+            arr2(&[[origin, top], [right, bottom]]),
+            stroke.color,
+            stroke.width,
+            stroke.linetype.clone(),
+            Some((1.0, 1.0, 1.0, 0.8)),
+        ),
+    ));
+    for (i, (text, color)) in entries.iter().enumerate() {
+        let y = top + 0.5 + i as f64 * LEGEND_LINE;
+        plotter.push(PlotItem::Rectangle(
+            99,
+            Rectangle::new(
+                arr2(&[[origin + 1.0, y + 1.0], [origin + 3.0, y + 3.0]]),
+                *color,
+                stroke.width,
+                stroke.linetype.clone(),
+                Some(*color),
+            ),
+        ));
+        plotter.push(text!(
+            arr1(&[origin + LEGEND_LINE, y + LEGEND_LINE / 2.0]),
+            0.0,
+            text.clone(),
+            effects
+        ));
     }
     Ok(plotter)
 }
+
+/// get the left end and the width of the time cursor bar.
+///
+/// The bar is placed in the margin above the border or, without border, above the
+/// bounds of the plotted items.
+pub fn cursor_bar(paper_size: (f64, f64), items: Option<Bounds>) -> ((f64, f64), f64) {
+    match items {
+        Some(((left, top), (right, _))) => (
+            (left, top - 2.0 * CURSOR_HEIGHT),
+            (right - left - CURSOR_TEXT).max(CURSOR_TEXT),
+        ),
+        None => (
+            (BORDER_MARGIN, BORDER_MARGIN / 2.0),
+            paper_size.0 - 2.0 * BORDER_MARGIN - CURSOR_TEXT,
+        ),
+    }
+}
+
+/// draw the time cursor as a bar with the time beside it.
+///
+/// The bar starts at the left end from `cursor_bar`, the position is the
+/// fraction of the width from 0.0 to 1.0.
+pub fn draw_cursor(
+    position: f64,
+    text: &str,
+    bar: ((f64, f64), f64),
+    theme: &Theme,
+) -> Result<Vec<PlotItem>, Error> {
+    let stroke: Stroke = theme.stroke("border_stroke")?;
+    let mut effects: Effects = theme.effects("border_effects")?;
+    effects.justify = vec![String::from("left")];
+    let ((left, y), width) = bar;
+    let end = left + width * position.clamp(0.0, 1.0);
+    let color = (stroke.color.0, stroke.color.1, stroke.color.2, 0.4);
+    Ok(vec![
+        PlotItem::Rectangle(
+            99,
+            Rectangle::new(
+                arr2(&[
+                    [left, y - CURSOR_HEIGHT / 2.0],
+                    [end, y + CURSOR_HEIGHT / 2.0],
+                ]),
+                color,
+                0.0,
+                stroke.linetype.clone(),
+                Some(color),
+            ),
+        ),
+        text!(arr1(&[end + 1.0, y]), 0.0, text.to_string(), effects),
+    ])
+}
+
+/// place the logo left of the title block, the logo is scaled to the height of the head.
+fn draw_logo(data: &[u8], corner: (f64, f64)) -> PlotItem {
+    let mut image = Image::new(arr1(&[0.0, 0.0]), 1.0, data.to_vec());
+    let (width, height) = image.size();
+    if height > 0.0 {
+        image.scale = (HEAD_HEIGHT - 2.0) / height;
+    }
+    let width = width * image.scale;
+    image.pos = arr1(&[
+        corner.0 - HEAD_WIDTH - 1.0 - width / 2.0,
+        corner.1 - HEAD_HEIGHT / 2.0,
+    ]);
+    PlotItem::Image(99, image)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        cursor_bar, draw_border, draw_cursor, legend_bounds, sheets, zone_letter, zones, Corner,
+        SheetInfo,
+    };
+    use crate::cairo_plotter::{paper, PlotItem};
+    use crate::theme::Theme;
+    use crate::variables::Variables;
+    use elektron_sexp::{Schema, TitleBlock};
+
+    #[test]
+    fn legend_corners() {
+        let theme = Theme::kicad_2000();
+        let entries = vec![(String::from("GND"), (0.0, 0.0, 0.0, 1.0))];
+        let top_left = legend_bounds(&entries, Corner::TopLeft, paper::A4, &theme).unwrap();
+        let top_right = legend_bounds(&entries, Corner::TopRight, paper::A4, &theme).unwrap();
+        let bottom_left = legend_bounds(&entries, Corner::BottomLeft, paper::A4, &theme).unwrap();
+        assert_eq!(top_left.0 .1, top_right.0 .1);
+        assert_eq!(paper::A4.0 - top_left.0 .0, top_right.1 .0);
+        assert_eq!(top_left.0 .0, bottom_left.0 .0);
+        assert_eq!(paper::A4.1 - top_left.0 .1, bottom_left.1 .1);
+    }
+    #[test]
+    fn title_block_variables() {
+        let project = HashMap::from([(String::from("RELEASE"), String::from("2024-01-31"))]);
+        let title_block = Some(TitleBlock {
+            date: String::from("${RELEASE}"),
+            rev: String::from("${PAPER}"),
+            ..TitleBlock::default()
+        });
+        let texts = |version: Option<&str>| -> Vec<String> {
+            let sheet = SheetInfo::default();
+            let variables = Variables::new(&project).page(&title_block, &sheet, "A4", version);
+            let theme = Theme::kicad_2000();
+            draw_border(&title_block, &sheet, &variables, None, paper::A4, &theme)
+                .unwrap()
+                .into_iter()
+                .filter_map(|item| match item {
+                    PlotItem::Text(_, text) => Some(text.text),
+                    _ => None,
+                })
+                .collect()
+        };
+        let texts_version = texts(Some("eeschema 20211123"));
+        assert!(texts_version.contains(&String::from("Date: 2024-01-31")));
+        assert!(texts_version.contains(&String::from("Rev: A4")));
+        assert!(texts_version.contains(&String::from("eeschema 20211123")));
+        assert_eq!(texts_version.len(), texts(None).len() + 1);
+    }
+    #[test]
+    fn cursor_margin() {
+        let theme = Theme::kicad_2000();
+        let bar = cursor_bar(paper::A4, None);
+        for item in draw_cursor(1.0, "t = 1ms", bar, &theme).unwrap() {
+            if let PlotItem::Rectangle(_, rectangle) = item {
+                assert!(rectangle.pts[[0, 1]] >= 0.0);
+                assert!(rectangle.pts[[1, 1]] <= super::BORDER_MARGIN);
+                assert!(rectangle.pts[[1, 0]] < paper::A4.0 - super::BORDER_MARGIN);
+            }
+        }
+        let ((left, y), _) = cursor_bar(paper::A4, Some(((20.0, 30.0), (120.0, 80.0))));
+        assert_eq!(20.0, left);
+        assert!(y < 30.0);
+    }
+    #[test]
+    fn zone_count() {
+        assert_eq!((6, 47.833333333333336), zones(287.0));
+        assert_eq!((1, 20.0), zones(20.0));
+    }
+    #[test]
+    fn zone_letters() {
+        assert_eq!("A", zone_letter(0));
+        assert_eq!("Z", zone_letter(25));
+        assert_eq!("AA", zone_letter(26));
+        assert_eq!("AB", zone_letter(27));
+        assert_eq!("BA", zone_letter(52));
+    }
+    #[test]
+    fn sheet_info() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let sheets = sheets(&doc, "summe.kicad_sch").unwrap();
+        assert_eq!(1, sheets[0].number);
+        assert_eq!(doc.pages(), sheets[0].count);
+        assert_eq!("/", sheets[0].path);
+        assert_eq!("summe.kicad_sch", sheets[0].filename);
+    }
+}
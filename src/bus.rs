@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref VECTOR: Regex = Regex::new(r"^[^\[\]{}\s]*\[\d+\.\.\d+\]$").unwrap();
+    static ref GROUP: Regex = Regex::new(r"^([^{}\s]*)\{([^{}]*)\}$").unwrap();
+}
+
+/// test if the label text names a bus, like `DATA[0..7]` or `I2C{SDA SCL}`.
+pub fn is_bus(text: &str) -> bool {
+    VECTOR.is_match(text) || GROUP.is_match(text)
+}
+
+/// replace the bus aliases in the members of a bus group.
+///
+/// `{I2C}` becomes `{SDA SCL}` when the alias `I2C` has the members `SDA` and `SCL`.
+pub fn resolve_aliases(text: &str, aliases: &HashMap<String, Vec<String>>) -> String {
+    if let Some(caps) = GROUP.captures(text) {
+        let members: Vec<String> = caps[2]
+            .split_whitespace()
+            .flat_map(|member| {
+                aliases
+                    .get(member)
+                    .cloned()
+                    .unwrap_or_else(|| vec![member.to_string()])
+            })
+            .collect();
+        format!("{}{{{}}}", &caps[1], members.join(" "))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{is_bus, resolve_aliases};
+
+    #[test]
+    fn bus_labels() {
+        assert!(is_bus("DATA[0..7]"));
+        assert!(is_bus("{SDA SCL}"));
+        assert!(is_bus("I2C{SDA SCL}"));
+        assert!(!is_bus("DATA0"));
+        assert!(!is_bus("DATA[0]"));
+    }
+    #[test]
+    fn bus_aliases() {
+        let mut aliases = HashMap::new();
+        aliases.insert(
+            String::from("I2C"),
+            vec![String::from("SDA"), String::from("SCL")],
+        );
+        assert_eq!("{SDA SCL INT}", resolve_aliases("{I2C INT}", &aliases));
+        assert_eq!("BUS{SDA SCL}", resolve_aliases("BUS{I2C}", &aliases));
+        assert_eq!("DATA[0..7]", resolve_aliases("DATA[0..7]", &aliases));
+    }
+}
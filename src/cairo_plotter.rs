@@ -1,25 +1,105 @@
-use crate::error::Error;
+use crate::error::{Error, Warning};
 use itertools::Itertools;
 use ndarray::{arr1, arr2, s, Array1, Array2};
+use pangocairo::glib::markup_escape_text;
 use pangocairo::{create_layout, pango::SCALE, show_layout, update_layout};
 use std::io::Write;
 extern crate cairo;
-use cairo::{Context, FontFace, FontSlant, FontWeight, Format, ImageSurface, SvgSurface};
+use cairo::{
+    Context, FontFace, FontSlant, FontWeight, Format, ImageSurface, PdfSurface, SvgSurface,
+};
 
 fn rgba_color(color: (f64, f64, f64, f64)) -> String {
     format!("#{:02X}{:02X}{:02X}{:02X}", (color.0*255.0) as u32, (color.1*255.0) as u32, (color.2*255.0) as u32, (color.3*255.0) as u32)
 }
 
+/// Paper sizes in mm, landscape orientation.
 pub mod paper {
+    pub const A5: (f64, f64) = (210.0, 148.0);
     pub const A4: (f64, f64) = (297.0, 210.0);
-}
+    pub const A3: (f64, f64) = (420.0, 297.0);
+    pub const A2: (f64, f64) = (594.0, 420.0);
+    pub const A1: (f64, f64) = (841.0, 594.0);
+    pub const A0: (f64, f64) = (1189.0, 841.0);
+    pub const ANSI_A: (f64, f64) = (279.4, 215.9);
+    pub const ANSI_B: (f64, f64) = (431.8, 279.4);
+    pub const ANSI_C: (f64, f64) = (558.8, 431.8);
+    pub const ANSI_D: (f64, f64) = (863.6, 558.8);
+    pub const ANSI_E: (f64, f64) = (1117.6, 863.6);
+    pub const US_LETTER: (f64, f64) = (279.4, 215.9);
+    pub const US_LEGAL: (f64, f64) = (355.6, 215.9);
+    pub const US_LEDGER: (f64, f64) = (431.8, 279.4);
+
+    const SIZES: [(&str, (f64, f64)); 14] = [
+        ("A5", A5),
+        ("A4", A4),
+        ("A3", A3),
+        ("A2", A2),
+        ("A1", A1),
+        ("A0", A0),
+        ("USLetter", US_LETTER),
+        ("USLegal", US_LEGAL),
+        ("USLedger", US_LEDGER),
+        ("A", ANSI_A),
+        ("B", ANSI_B),
+        ("C", ANSI_C),
+        ("D", ANSI_D),
+        ("E", ANSI_E),
+    ];
+
+    /// get the size of a KiCad paper definition.
+    ///
+    /// The paper is given like in the `paper` element of the schema, for example
+    /// `A3`, `A4 portrait` or `User 200 150`.
+    pub fn size(paper: &str) -> Option<(f64, f64)> {
+        parse(paper).map(|(_, size)| size)
+    }
 
-pub enum Surface {
-    ImageSurface(ImageSurface),
-    SvgSurface(SvgSurface),
-    // PdfSurface(PdfSurface), TODO
+    /// get the KiCad name and the size of a paper definition.
+    pub fn parse(paper: &str) -> Option<(&'static str, (f64, f64))> {
+        let tokens: Vec<&str> = paper
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"')
+            .filter(|t| !t.is_empty() && *t != "paper")
+            .collect();
+        let portrait = tokens.contains(&"portrait");
+        let (name, size) = match tokens.first() {
+            Some(&"User") => {
+                let width: f64 = tokens.get(1)?.parse().ok()?;
+                let height: f64 = tokens.get(2)?.parse().ok()?;
+                ("User", (width, height))
+            }
+            Some(name) => *SIZES.iter().find(|(n, _)| n == name)?,
+            None => return None,
+        };
+        if portrait && size.0 > size.1 {
+            Some((name, (size.1, size.0)))
+        } else {
+            Some((name, size))
+        }
+    }
+
+    /// get the KiCad paper name for the size, the orientation is ignored.
+    ///
+    /// The US papers have the same size as the ANSI papers and are preferred,
+    /// use `parse` when the paper definition is known.
+    pub fn name(size: (f64, f64)) -> String {
+        let (width, height) = if size.0 >= size.1 {
+            size
+        } else {
+            (size.1, size.0)
+        };
+        if let Some((name, _)) = SIZES
+            .iter()
+            .find(|(_, s)| (s.0 - width).abs() < 0.1 && (s.1 - height).abs() < 0.1)
+        {
+            name.to_string()
+        } else {
+            String::from("User")
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum ImageType {
     Svg,
     Png,
@@ -144,6 +224,31 @@ impl Circle {
     }
 }
 #[derive(Debug)]
+pub struct Bezier {
+    pub pts: Array2<f64>,
+    pub color: (f64, f64, f64, f64),
+    pub linewidth: f64,
+    pub linetype: String,
+    pub fill: Option<(f64, f64, f64, f64)>,
+}
+impl Bezier {
+    pub fn new(
+        pts: Array2<f64>,
+        color: (f64, f64, f64, f64),
+        linewidth: f64,
+        linetype: String,
+        fill: Option<(f64, f64, f64, f64)>,
+    ) -> Bezier {
+        Bezier {
+            pts,
+            color,
+            linewidth,
+            linetype,
+            fill,
+        }
+    }
+}
+#[derive(Debug)]
 pub struct Polyline {
     pub pts: Array2<f64>,
     pub color: (f64, f64, f64, f64),
@@ -181,6 +286,7 @@ pub struct Text {
     pub label: bool,
 }
 impl Text {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pos: Array1<f64>,
         angle: f64,
@@ -204,10 +310,52 @@ impl Text {
     }
 }
 
+/// get the Pango markup of the text, the font and the text are escaped.
+fn text_markup(text: &Text) -> String {
+    format!(
+        "<span face=\"{}\" foreground=\"{}\" size=\"{}\">{}</span>",
+        markup_escape_text(&text.font),
+        rgba_color(text.color),
+        (text.fontsize * 1024.0) as i32,
+        markup_escape_text(&text.text)
+    )
+}
+
+/// default resolution of the KiCad bitmaps.
+pub const BITMAP_PPI: f64 = 300.0;
+
+/// A PNG image, the position is the center of the image.
+#[derive(Debug)]
+pub struct Image {
+    pub pos: Array1<f64>,
+    /// size of one pixel in mm.
+    pub scale: f64,
+    pub data: Vec<u8>,
+}
+impl Image {
+    pub fn new(pos: Array1<f64>, scale: f64, data: Vec<u8>) -> Image {
+        Image { pos, scale, data }
+    }
+    /// get the image size in mm, the pixel size is taken from the PNG header.
+    pub fn size(&self) -> (f64, f64) {
+        if self.data.len() >= 24 && self.data[12..16] == *b"IHDR" {
+            let width =
+                u32::from_be_bytes([self.data[16], self.data[17], self.data[18], self.data[19]]);
+            let height =
+                u32::from_be_bytes([self.data[20], self.data[21], self.data[22], self.data[23]]);
+            (width as f64 * self.scale, height as f64 * self.scale)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum PlotItem {
     Arc(usize, Arc),
+    Bezier(usize, Bezier),
     Circle(usize, Circle),
+    Image(usize, Image),
     Line(usize, Line),
     Rectangle(usize, Rectangle),
     Polyline(usize, Polyline),
@@ -229,20 +377,20 @@ macro_rules! fill {
     ($context:expr, $fill:expr) => {
         if let Some(fill) = $fill {
             $context.set_source_rgba(fill.0, fill.1, fill.2, fill.3);
-            $context.fill().unwrap();
+            $context.fill()?;
         }
     };
 }
 macro_rules! effects {
     ($context:expr, $effects:expr) => {
         $context.set_font_size($effects.fontsize);
-        let face = FontFace::toy_create(
+        if let Ok(face) = FontFace::toy_create(
             $effects.font.as_str(),
             FontSlant::Normal,
             FontWeight::Normal,
-        )
-        .unwrap();
-        $context.set_font_face(&face);
+        ) {
+            $context.set_font_face(&face);
+        }
         $context.set_source_rgba(0.0, 0.0, 0.0, 1.0); //TODO
     };
 }
@@ -259,56 +407,143 @@ pub trait Plotter {
     ) -> Result<(), Error>;
     fn paper(&mut self, paper: String);
     fn get_paper(&self) -> (f64, f64);
-    fn draw(&mut self, context: &Context);
+    fn draw(&mut self, context: &Context) -> Result<(), Error>;
 }
 
-/// Plotter implemntation for SVG and PDF file.
+/// Plotter implemntation for SVG, PNG and PDF file.
 pub struct CairoPlotter<'a> {
     items: &'a Vec<PlotItem>,
     context: Context,
     paper_size: (f64, f64),
+    warnings: Vec<Warning>,
+    /// the cropped area in mm.
+    view: Option<Array2<f64>>,
 }
 impl<'a> CairoPlotter<'a> {
-    pub fn new(items: &'a Vec<PlotItem>) -> CairoPlotter {
+    pub fn new(
+        items: &'a Vec<PlotItem>,
+        paper_size: (f64, f64),
+    ) -> Result<CairoPlotter<'a>, Error> {
         let surface = ImageSurface::create(
             Format::Rgb24,
-            (297.0 * 72.0 / 25.4) as i32,
-            (210.0 * 72.0 / 25.4) as i32,
-        )
-        .unwrap();
-        let context = Context::new(&surface).unwrap();
+            (paper_size.0 * 72.0 / 25.4) as i32,
+            (paper_size.1 * 72.0 / 25.4) as i32,
+        )?;
+        let context = Context::new(&surface)?;
         context.scale(72.0 / 25.4, 72.0 / 25.4);
-        CairoPlotter {
+        Ok(CairoPlotter {
             items,
             context,
-            paper_size: paper::A4,
+            paper_size,
+            warnings: Vec::new(),
+            view: None,
+        })
+    }
+    /// take the warnings collected while drawing.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+    /// get the bounds of the item, the text size is taken from the context.
+    fn item_bounds(&self, item: &PlotItem) -> Option<Array2<f64>> {
+        match item {
+            PlotItem::Text(_, text) => {
+                let outline = self.text_size(text);
+                let mut x = text.pos[0];
+                let mut y = text.pos[1];
+                if text.align.contains(&String::from("right")) {
+                    x -= outline[0];
+                } else if text.align.contains(&String::from("top")) {
+                    y -= outline[1];
+                } else if !text.align.contains(&String::from("left"))
+                    && !text.align.contains(&String::from("bottom"))
+                {
+                    x -= outline[0] / 2.0;
+                    y -= outline[1] / 2.0;
+                }
+                Option::from(arr2(&[[x, y], [x + outline[0], y + outline[1]]]))
+            }
+            _ => outline(item),
+        }
+    }
+    /// test if the item intersects the view.
+    fn visible(&self, item: &PlotItem) -> bool {
+        match (&self.view, self.item_bounds(item)) {
+            (Some(view), Some(bounds)) => {
+                let bounds = arr_outline(&bounds);
+                bounds[[0, 0]] <= view[[1, 0]]
+                    && bounds[[1, 0]] >= view[[0, 0]]
+                    && bounds[[0, 1]] <= view[[1, 1]]
+                    && bounds[[1, 1]] >= view[[0, 1]]
+            }
+            _ => true,
         }
     }
-    fn arr_outline(&self, boxes: &Array2<f64>) -> Array2<f64> {
-        let axis1 = boxes.slice(s![.., 0]);
-        let axis2 = boxes.slice(s![.., 1]);
-        arr2(&[
-            [
-                *axis1
-                    .iter()
-                    .min_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap(),
-                *axis2
-                    .iter()
-                    .min_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap(),
-            ],
-            [
-                *axis1
-                    .iter()
-                    .max_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap(),
-                *axis2
-                    .iter()
-                    .max_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap(),
-            ],
-        ])
+    /// crop the plot to the view in mm, the items outside of the view are not drawn.
+    pub fn crop(&mut self, view: ((f64, f64), (f64, f64))) {
+        self.view = Some(arr2(&[[view.0 .0, view.0 .1], [view.1 .0, view.1 .1]]));
+    }
+}
+
+/// the estimated width of a character relative to the font size.
+const CHAR_WIDTH: f64 = 0.6;
+
+/// estimate the width of the text from the number of characters.
+pub(crate) fn text_width(text: &str, font_size: f64) -> f64 {
+    text.chars().count() as f64 * font_size * CHAR_WIDTH
+}
+
+/// get the array of the points.
+pub(crate) fn points(pts: &[(f64, f64)]) -> Array2<f64> {
+    Array2::from_shape_fn(
+        (pts.len(), 2),
+        |(i, j)| if j == 0 { pts[i].0 } else { pts[i].1 },
+    )
+}
+
+fn arr_outline(boxes: &Array2<f64>) -> Array2<f64> {
+    if boxes.nrows() == 0 {
+        return Array2::zeros((2, 2));
+    }
+    let axis1 = boxes.slice(s![.., 0]);
+    let axis2 = boxes.slice(s![.., 1]);
+    arr2(&[
+        [
+            axis1.fold(f64::INFINITY, |a, b| a.min(*b)),
+            axis2.fold(f64::INFINITY, |a, b| a.min(*b)),
+        ],
+        [
+            axis1.fold(f64::NEG_INFINITY, |a, b| a.max(*b)),
+            axis2.fold(f64::NEG_INFINITY, |a, b| a.max(*b)),
+        ],
+    ])
+}
+
+/// get the outline of the item, a text is reduced to its position.
+pub(crate) fn outline(item: &PlotItem) -> Option<Array2<f64>> {
+    match item {
+        PlotItem::Arc(_, arc) => Option::from(arc_outline(&arc.start, &arc.mid, &arc.end)),
+        PlotItem::Bezier(_, bezier) => Option::from(arr_outline(&bezier.pts)),
+        PlotItem::Line(_, line) => Option::from(arr2(&[
+            [line.pts[[0, 0]], line.pts[[0, 1]]],
+            [line.pts[[1, 0]], line.pts[[1, 1]]],
+        ])),
+        PlotItem::Text(_, text) => Option::from(arr2(&[[text.pos[0], text.pos[1]]])),
+        PlotItem::Circle(_, circle) => Option::from(arr2(&[
+            [circle.pos[0] - circle.radius, circle.pos[1] - circle.radius],
+            [circle.pos[0] + circle.radius, circle.pos[1] + circle.radius],
+        ])),
+        PlotItem::Polyline(_, polyline) => Option::from(arr_outline(&polyline.pts)),
+        PlotItem::Rectangle(_, rect) => Option::from(arr2(&[
+            [rect.pts[[0, 0]], rect.pts[[0, 1]]],
+            [rect.pts[[1, 0]], rect.pts[[1, 1]]],
+        ])),
+        PlotItem::Image(_, image) => {
+            let size = image.size();
+            Option::from(arr2(&[
+                [image.pos[0] - size.0 / 2.0, image.pos[1] - size.1 / 2.0],
+                [image.pos[0] + size.0 / 2.0, image.pos[1] + size.1 / 2.0],
+            ]))
+        }
     }
 }
 
@@ -316,55 +551,23 @@ impl<'a> Plotter for CairoPlotter<'a> {
     /// get the text size in pixels.
     fn text_size(&self, item: &Text) -> Array1<f64> {
         effects!(self.context, item);
-        let extends = self.context.text_extents(item.text.as_str()).unwrap();
-        arr1(&[extends.width, extends.height])
+        if let Ok(extends) = self.context.text_extents(item.text.as_str()) {
+            arr1(&[extends.width, extends.height])
+        } else {
+            arr1(&[0.0, 0.0])
+        }
     }
     /// Calculate the drawing area.
     fn bounds(&self) -> Array2<f64> {
         let mut __bounds: Array2<f64> = Array2::default((0, 2));
         self.items.iter().for_each(|item| {
-            let arr: Option<Array2<f64>> = match item {
-                PlotItem::Arc(_, arc) => Option::from(arr2(&[
-                    [arc.start[0], arc.start[1]],
-                    [arc.end[0], arc.end[1]],
-                ])),
-                PlotItem::Line(_, line) => Option::from(arr2(&[
-                    [line.pts[[0, 0]], line.pts[[0, 1]]],
-                    [line.pts[[1, 0]], line.pts[[1, 1]]],
-                ])),
-                PlotItem::Text(_, text) => {
-                    let outline = self.text_size(text);
-                    let mut x = text.pos[0];
-                    let mut y = text.pos[1];
-                    if text.align.contains(&String::from("right")) {
-                        x -= outline[0];
-                    } else if text.align.contains(&String::from("top")) {
-                        y -= outline[1];
-                    } else if !text.align.contains(&String::from("left"))
-                        && !text.align.contains(&String::from("bottom"))
-                    {
-                        x -= outline[0] / 2.0;
-                        y -= outline[1] / 2.0;
-                    }
-                    Option::from(arr2(&[[x, y], [x + outline[0], y + outline[1]]]))
-                }
-                PlotItem::Circle(_, circle) => Option::from(arr2(&[
-                    [circle.pos[0] - circle.radius, circle.pos[1] - circle.radius],
-                    [circle.pos[0] + circle.radius, circle.pos[1] + circle.radius],
-                ])),
-                PlotItem::Polyline(_, polyline) => Option::from(self.arr_outline(&polyline.pts)),
-                PlotItem::Rectangle(_, rect) => Option::from(arr2(&[
-                    [rect.pts[[0, 0]], rect.pts[[0, 1]]],
-                    [rect.pts[[1, 0]], rect.pts[[1, 1]]],
-                ])),
-            };
-            if let Some(array) = arr {
+            if let Some(array) = self.item_bounds(item) {
                 for row in array.rows() {
                     __bounds.push_row(row).unwrap();
                 }
             }
         });
-        self.arr_outline(&__bounds)
+        arr_outline(&__bounds)
     }
 
     fn plot(
@@ -374,49 +577,50 @@ impl<'a> Plotter for CairoPlotter<'a> {
         scale: f64,
         image_type: &ImageType,
     ) -> Result<(), Error> {
-        if border {
+        if border && self.view.is_none() {
             match image_type {
                 ImageType::Svg => {
                     let surface = SvgSurface::for_stream(
                         self.paper_size.0 * 96.0 / 25.4,
                         self.paper_size.1 * 96.0 / 25.4,
                         file,
-                    )
-                    .unwrap();
-                    let context = Context::new(&surface).unwrap();
+                    )?;
+                    let context = Context::new(&surface)?;
                     context.scale(96.0 / 25.4, 96.0 / 25.4);
-                    self.draw(&context);
-                    surface.finish_output_stream().unwrap();
+                    self.draw(&context)?;
+                    surface.finish_output_stream()?;
                 }
                 ImageType::Png => {
                     let surface = ImageSurface::create(
                         Format::Rgb24,
                         (self.paper_size.0 * 96.0 / 25.4) as i32,
                         (self.paper_size.1 * 96.0 / 25.4) as i32,
-                    )
-                    .unwrap();
-                    let context = Context::new(&surface).unwrap();
+                    )?;
+                    let context = Context::new(&surface)?;
                     context.scale(96.0 / 25.4, 96.0 / 25.4);
-                    self.draw(&context);
+                    self.draw(&context)?;
                     surface.write_to_png(&mut file)?;
                 }
                 ImageType::Pdf => {
-                    todo!();
-                    /*    let surface = PdfSurface::for_stream(
-                        self.paper_size.0 * 96.0 / 25.4,
-                        self.paper_size.1 * 96.0 / 25.4,
+                    let surface = PdfSurface::for_stream(
+                        self.paper_size.0 * 72.0 / 25.4,
+                        self.paper_size.1 * 72.0 / 25.4,
                         file,
-                    )
-                    .unwrap();
-                    let context = Context::new(&surface).unwrap();
-                    context.scale(96.0 / 25.4, 96.0 / 25.4);
-                    (context, Surface::PdfSurface(surface)) */
+                    )?;
+                    let context = Context::new(&surface)?;
+                    context.scale(72.0 / 25.4, 72.0 / 25.4);
+                    self.draw(&context)?;
+                    surface.finish_output_stream()?;
                 }
             }
         } else {
+            let size = if let Some(view) = &self.view {
+                view.clone()
+            } else {
+                self.bounds() + arr2(&[[-2.54, -2.54], [2.54, 2.54]])
+            };
             match image_type {
                 ImageType::Svg => {
-                    let size = self.bounds() + arr2(&[[-2.54, -2.54], [2.54, 2.54]]);
                     let surface = SvgSurface::for_stream(
                         (size[[1, 0]] - size[[0, 0]]) * 72.0 / 25.4 * scale,
                         (size[[1, 1]] - size[[0, 1]]) * 72.0 / 25.4 * scale,
@@ -425,11 +629,10 @@ impl<'a> Plotter for CairoPlotter<'a> {
                     let context = Context::new(&surface)?;
                     context.scale(72.0 / 25.4 * scale, 72.0 / 25.4 * scale);
                     context.translate(-size[[0, 0]], -size[[0, 1]]);
-                    self.draw(&context);
-                    surface.finish_output_stream().unwrap();
+                    self.draw(&context)?;
+                    surface.finish_output_stream()?;
                 }
                 ImageType::Png => {
-                    let size = self.bounds() + arr2(&[[-2.54, -2.54], [2.54, 2.54]]);
                     let surface = ImageSurface::create(
                         Format::Rgb24,
                         ((size[[1, 0]] - size[[0, 0]]) * 72.0 / 25.4 * scale) as i32,
@@ -438,210 +641,427 @@ impl<'a> Plotter for CairoPlotter<'a> {
                     let context = Context::new(&surface)?;
                     context.scale(72.0 / 25.4 * scale, 72.0 / 25.4 * scale);
                     context.translate(-size[[0, 0]], -size[[0, 1]]);
-                    self.draw(&context);
+                    self.draw(&context)?;
                     surface.write_to_png(&mut file)?;
                 }
                 ImageType::Pdf => {
-                    todo!();
+                    let surface = PdfSurface::for_stream(
+                        (size[[1, 0]] - size[[0, 0]]) * 72.0 / 25.4 * scale,
+                        (size[[1, 1]] - size[[0, 1]]) * 72.0 / 25.4 * scale,
+                        file,
+                    )?;
+                    let context = Context::new(&surface)?;
+                    context.scale(72.0 / 25.4 * scale, 72.0 / 25.4 * scale);
+                    context.translate(-size[[0, 0]], -size[[0, 1]]);
+                    self.draw(&context)?;
+                    surface.finish_output_stream()?;
                 }
             }
         };
         Ok(())
     }
 
-    fn paper(&mut self, paper_size: String) {
-        if paper_size == "A4" {
-            self.paper_size = paper::A4;
-        } // TODO other paper sizes
+    fn paper(&mut self, paper: String) {
+        match paper::size(&paper) {
+            Some(size) => self.paper_size = size,
+            None => self.warnings.push(Warning::UnknownPaper(paper)),
+        }
     }
     fn get_paper(&self) -> (f64, f64) {
         self.paper_size
     }
 
-    fn draw(&mut self, context: &Context) {
+    fn draw(&mut self, context: &Context) -> Result<(), Error> {
         context.set_source_rgb(1.0, 1.0, 1.0);
-        context.paint().unwrap();
+        context.paint()?;
+        if let Some(view) = &self.view {
+            context.rectangle(
+                view[[0, 0]],
+                view[[0, 1]],
+                view[[1, 0]] - view[[0, 0]],
+                view[[1, 1]] - view[[0, 1]],
+            );
+            context.clip();
+        }
 
         //draw the rest
-        self.items
-            .iter()
-            .sorted_by(|a, b| {
-                let za = match a {
-                    PlotItem::Arc(z, _) => z,
-                    PlotItem::Line(z, _) => z,
-                    PlotItem::Text(z, _) => z,
-                    PlotItem::Circle(z, _) => z,
-                    PlotItem::Polyline(z, _) => z,
-                    PlotItem::Rectangle(z, _) => z,
-                };
-                let zb = match b {
-                    PlotItem::Arc(z, _) => z,
-                    PlotItem::Line(z, _) => z,
-                    PlotItem::Text(z, _) => z,
-                    PlotItem::Circle(z, _) => z,
-                    PlotItem::Polyline(z, _) => z,
-                    PlotItem::Rectangle(z, _) => z,
-                };
-
-                Ord::cmp(&za, &zb)
-            })
-            .for_each(|item| {
-                //for item in &self.items {
-                match item {
-                    PlotItem::Line(_, line) => {
-                        stroke!(context, line);
-                        match line.linecap {
-                            LineCap::Butt => context.set_line_cap(cairo::LineCap::Butt),
-                            LineCap::Round => context.set_line_cap(cairo::LineCap::Round),
-                            LineCap::Square => context.set_line_cap(cairo::LineCap::Square),
-                        }
-                        context.move_to(line.pts[[0, 0]], line.pts[[0, 1]]);
-                        context.line_to(line.pts[[1, 0]], line.pts[[1, 1]]);
-                        context.stroke().unwrap();
+        let items = self.items;
+        for item in items.iter().sorted_by(|a, b| {
+            let za = match a {
+                PlotItem::Arc(z, _) => z,
+                PlotItem::Bezier(z, _) => z,
+                PlotItem::Line(z, _) => z,
+                PlotItem::Text(z, _) => z,
+                PlotItem::Circle(z, _) => z,
+                PlotItem::Image(z, _) => z,
+                PlotItem::Polyline(z, _) => z,
+                PlotItem::Rectangle(z, _) => z,
+            };
+            let zb = match b {
+                PlotItem::Arc(z, _) => z,
+                PlotItem::Bezier(z, _) => z,
+                PlotItem::Line(z, _) => z,
+                PlotItem::Text(z, _) => z,
+                PlotItem::Circle(z, _) => z,
+                PlotItem::Image(z, _) => z,
+                PlotItem::Polyline(z, _) => z,
+                PlotItem::Rectangle(z, _) => z,
+            };
+
+            Ord::cmp(&za, &zb)
+        }) {
+            if !self.visible(item) {
+                continue;
+            }
+            match item {
+                PlotItem::Line(_, line) => {
+                    stroke!(context, line);
+                    match line.linecap {
+                        LineCap::Butt => context.set_line_cap(cairo::LineCap::Butt),
+                        LineCap::Round => context.set_line_cap(cairo::LineCap::Round),
+                        LineCap::Square => context.set_line_cap(cairo::LineCap::Square),
                     }
-                    PlotItem::Polyline(_, line) => {
-                        stroke!(context, line);
-                        let mut first: bool = true;
-                        for pos in line.pts.rows() {
-                            if first {
-                                context.move_to(pos[0], pos[1]);
-                                first = false;
-                            } else {
-                                context.line_to(pos[0], pos[1]);
-                                context.stroke_preserve().unwrap();
-                            }
+                    context.move_to(line.pts[[0, 0]], line.pts[[0, 1]]);
+                    context.line_to(line.pts[[1, 0]], line.pts[[1, 1]]);
+                    context.stroke()?;
+                }
+                PlotItem::Polyline(_, line) => {
+                    stroke!(context, line);
+                    let mut first: bool = true;
+                    for pos in line.pts.rows() {
+                        if first {
+                            context.move_to(pos[0], pos[1]);
+                            first = false;
+                        } else {
+                            context.line_to(pos[0], pos[1]);
+                            context.stroke_preserve()?;
                         }
-                        fill!(context, &line.fill);
-                        context.stroke().unwrap()
                     }
-                    PlotItem::Rectangle(_, rectangle) => {
-                        stroke!(context, rectangle);
-                        context.rectangle(
-                            rectangle.pts[[0, 0]],
-                            rectangle.pts[[0, 1]],
-                            rectangle.pts[[1, 0]] - rectangle.pts[[0, 0]],
-                            rectangle.pts[[1, 1]] - rectangle.pts[[0, 1]],
-                        );
-                        context.stroke_preserve().unwrap();
-                        fill!(context, &rectangle.fill);
-                        context.stroke().unwrap()
+                    fill!(context, &line.fill);
+                    context.stroke()?;
+                }
+                PlotItem::Rectangle(_, rectangle) => {
+                    stroke!(context, rectangle);
+                    context.rectangle(
+                        rectangle.pts[[0, 0]],
+                        rectangle.pts[[0, 1]],
+                        rectangle.pts[[1, 0]] - rectangle.pts[[0, 0]],
+                        rectangle.pts[[1, 1]] - rectangle.pts[[0, 1]],
+                    );
+                    context.stroke_preserve()?;
+                    fill!(context, &rectangle.fill);
+                    context.stroke()?;
+                }
+                PlotItem::Circle(_, circle) => {
+                    stroke!(context, circle);
+                    context.arc(circle.pos[0], circle.pos[1], circle.radius, 0., 10.);
+                    context.stroke_preserve()?;
+                    fill!(context, &circle.fill);
+                    context.stroke()?;
+                }
+                PlotItem::Arc(_, arc) => {
+                    stroke!(context, arc);
+                    if let Some((center, radius)) = arc_center(&arc.start, &arc.mid, &arc.end) {
+                        let angle = |pt: &Array1<f64>| (pt[1] - center[1]).atan2(pt[0] - center[0]);
+                        let (start, mid, end) =
+                            (angle(&arc.start), angle(&arc.mid), angle(&arc.end));
+                        let tau = 2.0 * std::f64::consts::PI;
+                        // draw the arc in the direction which passes the mid point.
+                        if (mid - start).rem_euclid(tau) < (end - start).rem_euclid(tau) {
+                            context.arc(center[0], center[1], radius, start, end);
+                        } else {
+                            context.arc_negative(center[0], center[1], radius, start, end);
+                        }
+                    } else {
+                        context.move_to(arc.start[0], arc.start[1]);
+                        context.line_to(arc.end[0], arc.end[1]);
                     }
-                    PlotItem::Circle(_, circle) => {
-                        stroke!(context, circle);
-                        context.arc(circle.pos[0], circle.pos[1], circle.radius, 0., 10.);
-                        context.stroke_preserve().unwrap();
-                        fill!(context, &circle.fill);
-                        context.stroke().unwrap()
+                    context.stroke_preserve()?;
+                    fill!(context, &arc.fill);
+                    context.stroke()?;
+                }
+                PlotItem::Bezier(_, bezier) => {
+                    stroke!(context, bezier);
+                    let pts = &bezier.pts;
+                    if pts.nrows() > 0 {
+                        context.move_to(pts[[0, 0]], pts[[0, 1]]);
+                        let mut i = 1;
+                        while i + 2 < pts.nrows() {
+                            context.curve_to(
+                                pts[[i, 0]],
+                                pts[[i, 1]],
+                                pts[[i + 1, 0]],
+                                pts[[i + 1, 1]],
+                                pts[[i + 2, 0]],
+                                pts[[i + 2, 1]],
+                            );
+                            i += 3;
+                        }
                     }
-                    PlotItem::Arc(_, arc) => {
-                        stroke!(context, arc);
-                        context.arc(arc.start[0], arc.start[1], arc.mid[1], 0., 10.);
-                        context.stroke_preserve().unwrap();
-                        fill!(context, &arc.fill);
-                        context.stroke().unwrap()
+                    context.stroke_preserve()?;
+                    fill!(context, &bezier.fill);
+                    context.stroke()?;
+                }
+                PlotItem::Image(_, image) => {
+                    if let Ok(surface) = ImageSurface::create_from_png(&mut image.data.as_slice()) {
+                        let size = image.size();
+                        context.save()?;
+                        context.translate(image.pos[0] - size.0 / 2.0, image.pos[1] - size.1 / 2.0);
+                        context.scale(image.scale, image.scale);
+                        context.set_source_surface(&surface, 0.0, 0.0)?;
+                        context.paint()?;
+                        context.restore()?;
+                    } else {
+                        self.warnings
+                            .push(Warning::UnsupportedElement(String::from("image data")));
                     }
-                    PlotItem::Text(_, text) => {
-                        context.save().unwrap();
-                        let layout = create_layout(&self.context);
-                        if let Some(layout) = layout {
-                            let markup = format!("<span face=\"{}\" foreground=\"{}\" size=\"{}\">{}</span>", 
-                                text.font, rgba_color(text.color), (text.fontsize * 1024.0) as i32, text.text);
-                            layout.set_markup(markup.as_str());
-                            update_layout(context, &layout);
-
-                            let outline: (i32, i32) = layout.size();
-                            let outline = (outline.0 as f64 / SCALE as f64, outline.1 as f64 / SCALE as f64);
-                            let mut x = text.pos[0];
-                            let mut y = text.pos[1];
-
-                            if !text.label {
-                                if text.angle == 0.0 || text.angle == 180.0 {
-                                    if text.align.contains(&String::from("right")) {
-                                        x -= outline.0 as f64;
-                                    } else if !text.align.contains(&String::from("left")) {
-                                        x -= outline.0 as f64 / 2.0;
-                                    }
-                                    if text.align.contains(&String::from("bottom")) {
-                                        y -= outline.1 as f64;
-                                    } else if !text.align.contains(&String::from("top")) {
-                                        y -= outline.1 as f64 / 2.0;
-                                    }
-                                } else if text.angle == 90.0 || text.angle == 270.0 {
-                                    if text.align.contains(&String::from("right")) {
-                                        y += outline.0 as f64;
-                                    } else if !text.align.contains(&String::from("left")) {
-                                        y += outline.0 as f64 / 2.0;
-                                    }
-                                    if text.align.contains(&String::from("bottom")) {
-                                        x -= outline.1 as f64;
-                                    } else if !text.align.contains(&String::from("top")) {
-                                        x -= outline.1 as f64 / 2.0;
-                                    }
-                                } else {
-                                    println!("text angle is: {} ({})", text.angle, text.text);
-                                }
-                                context.move_to(x, y);
-                                let angle = if text.angle >= 180.0 {
-                                    text.angle - 180.0 
-                                } else { text.angle };
-                                context.rotate(-angle * std::f64::consts::PI / 180.0);
-                                show_layout(context, &layout);
-                                context.stroke().unwrap();
-                            } else {
-                                let label_left = 0.4;
-                                let label_up = 0.1;
-                                let contur = arr2(&[
-                                    [0.0, 0.], 
-                                    [2.0*label_left, -outline.1/2.0 - label_up],
-                                    [3.0*label_left + outline.0, -outline.1/2.0 - label_up],
-                                    [3.0*label_left + outline.0, outline.1/2.0 + label_up],
-                                    [2.0*label_left, outline.1/2.0 + label_up],
-                                    [0.0, 0.0]
-                                ]);
-                                let theta = -text.angle.to_radians();
-                                let rot = arr2(&[[theta.cos(), -theta.sin()], [theta.sin(), theta.cos()]]);
-                                let verts: Array2<f64> = contur.dot(&rot);
-                                let verts = &text.pos + verts;
-                                context.move_to(text.pos[0], text.pos[1]);
-                                for row in verts.rows() {
-                                    context.line_to(row[0], row[1]);
-                                }
-                                context.stroke().unwrap();
+                }
+                PlotItem::Text(_, text) => {
+                    context.save()?;
+                    let layout = create_layout(&self.context);
+                    if let Some(layout) = layout {
+                        layout.set_markup(text_markup(text).as_str());
+                        update_layout(context, &layout);
 
-                                //adjust the text
-                                if text.angle == 0.0 {
-                                    x += 2.0*label_left;
-                                    y -= outline.1 / 2.0;
-                                } else if text.angle == 180.0 {
-                                    x -= 2.0*label_left + outline.0;
+                        let outline: (i32, i32) = layout.size();
+                        let outline = (
+                            outline.0 as f64 / SCALE as f64,
+                            outline.1 as f64 / SCALE as f64,
+                        );
+                        let mut x = text.pos[0];
+                        let mut y = text.pos[1];
+
+                        if !text.label {
+                            if text.angle == 0.0 || text.angle == 180.0 {
+                                if text.align.contains(&String::from("right")) {
+                                    x -= outline.0;
+                                } else if !text.align.contains(&String::from("left")) {
+                                    x -= outline.0 / 2.0;
+                                }
+                                if text.align.contains(&String::from("bottom")) {
+                                    y -= outline.1;
+                                } else if !text.align.contains(&String::from("top")) {
                                     y -= outline.1 / 2.0;
-                                } //TODO 90, 270
-                                context.move_to(x, y);
-                                let angle = if text.angle >= 180.0 {
-                                    text.angle - 180.0 
-                                } else { text.angle };
-                                context.rotate(-angle * std::f64::consts::PI / 180.0);
-                                show_layout(context, &layout);
-                                context.stroke().unwrap();
+                                }
+                            } else if text.angle == 90.0 || text.angle == 270.0 {
+                                if text.align.contains(&String::from("right")) {
+                                    y += outline.0;
+                                } else if !text.align.contains(&String::from("left")) {
+                                    y += outline.0 / 2.0;
+                                }
+                                if text.align.contains(&String::from("bottom")) {
+                                    x -= outline.1;
+                                } else if !text.align.contains(&String::from("top")) {
+                                    x -= outline.1 / 2.0;
+                                }
+                            } else {
+                                self.warnings.push(Warning::UnsupportedElement(format!(
+                                    "text angle {} ({}), text is not aligned",
+                                    text.angle, text.text
+                                )));
                             }
-
+                            context.move_to(x, y);
+                            let angle = if text.angle >= 180.0 {
+                                text.angle - 180.0
+                            } else {
+                                text.angle
+                            };
+                            context.rotate(-angle * std::f64::consts::PI / 180.0);
+                            show_layout(context, &layout);
+                            context.stroke()?;
                         } else {
-                            panic!("can not get pangocairo layout");
+                            let label_left = 0.4;
+                            let label_up = 0.1;
+                            let contur = arr2(&[
+                                [0.0, 0.],
+                                [2.0 * label_left, -outline.1 / 2.0 - label_up],
+                                [3.0 * label_left + outline.0, -outline.1 / 2.0 - label_up],
+                                [3.0 * label_left + outline.0, outline.1 / 2.0 + label_up],
+                                [2.0 * label_left, outline.1 / 2.0 + label_up],
+                                [0.0, 0.0],
+                            ]);
+                            let theta = -text.angle.to_radians();
+                            let rot =
+                                arr2(&[[theta.cos(), -theta.sin()], [theta.sin(), theta.cos()]]);
+                            let verts: Array2<f64> = contur.dot(&rot);
+                            let verts = &text.pos + verts;
+                            context.move_to(text.pos[0], text.pos[1]);
+                            for row in verts.rows() {
+                                context.line_to(row[0], row[1]);
+                            }
+                            context.stroke()?;
+
+                            //adjust the text
+                            if text.angle == 0.0 {
+                                x += 2.0 * label_left;
+                                y -= outline.1 / 2.0;
+                            } else if text.angle == 180.0 {
+                                x -= 2.0 * label_left + outline.0;
+                                y -= outline.1 / 2.0;
+                            } //TODO 90, 270
+                            context.move_to(x, y);
+                            let angle = if text.angle >= 180.0 {
+                                text.angle - 180.0
+                            } else {
+                                text.angle
+                            };
+                            context.rotate(-angle * std::f64::consts::PI / 180.0);
+                            show_layout(context, &layout);
+                            context.stroke()?;
                         }
-                        context.restore().unwrap();
+                    } else {
+                        // without pango the cairo toy font api is used.
+                        self.warnings.push(Warning::FontFallback(text.font.clone()));
+                        effects!(context, text);
+                        context.set_source_rgba(
+                            text.color.0,
+                            text.color.1,
+                            text.color.2,
+                            text.color.3,
+                        );
+                        context.move_to(text.pos[0], text.pos[1]);
+                        context.rotate(-text.angle.to_radians());
+                        context.show_text(text.text.as_str())?;
                     }
+                    context.restore()?;
                 }
-            });
+            }
+        }
+        Ok(())
     }
 }
 
+/// get the center and the radius of the circle through the three arc points.
+///
+/// Returns `None` when the points are on a line.
+fn arc_center(
+    start: &Array1<f64>,
+    mid: &Array1<f64>,
+    end: &Array1<f64>,
+) -> Option<(Array1<f64>, f64)> {
+    let d = 2.0
+        * (start[0] * (mid[1] - end[1])
+            + mid[0] * (end[1] - start[1])
+            + end[0] * (start[1] - mid[1]));
+    if d.abs() < 1e-9 {
+        return None;
+    }
+    let sq = |pt: &Array1<f64>| pt[0] * pt[0] + pt[1] * pt[1];
+    let x = (sq(start) * (mid[1] - end[1])
+        + sq(mid) * (end[1] - start[1])
+        + sq(end) * (start[1] - mid[1]))
+        / d;
+    let y = (sq(start) * (end[0] - mid[0])
+        + sq(mid) * (start[0] - end[0])
+        + sq(end) * (mid[0] - start[0]))
+        / d;
+    let radius = ((start[0] - x).powi(2) + (start[1] - y).powi(2)).sqrt();
+    Some((arr1(&[x, y]), radius))
+}
+
+/// get the points of the arc and the extreme points of the circle on the arc.
+fn arc_outline(start: &Array1<f64>, mid: &Array1<f64>, end: &Array1<f64>) -> Array2<f64> {
+    let mut outline = arr2(&[[start[0], start[1]], [mid[0], mid[1]], [end[0], end[1]]]);
+    if let Some((center, radius)) = arc_center(start, mid, end) {
+        let angle = |pt: &Array1<f64>| (pt[1] - center[1]).atan2(pt[0] - center[0]);
+        // the angle from the start in the counterclockwise direction.
+        let sweep = |a: f64| (a - angle(start)).rem_euclid(2.0 * std::f64::consts::PI);
+        let (to_mid, to_end) = (sweep(angle(mid)), sweep(angle(end)));
+        for quadrant in 0..4 {
+            let a = quadrant as f64 * std::f64::consts::FRAC_PI_2;
+            let on_arc = if to_mid < to_end {
+                sweep(a) < to_end
+            } else {
+                sweep(a) > to_end
+            };
+            if on_arc {
+                outline
+                    .push_row(
+                        arr1(&[center[0] + radius * a.cos(), center[1] + radius * a.sin()]).view(),
+                    )
+                    .unwrap();
+            }
+        }
+    }
+    outline
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::cairo_plotter::rgba_color;
+    use crate::cairo_plotter::{
+        arc_center, arc_outline, arr_outline, paper, rgba_color, text_markup, Text,
+    };
+    use ndarray::arr1;
 
     #[test]
     fn convert_color() {
         assert_eq!("#000000FF", rgba_color((0.0, 0.0, 0.0, 1.0)));
         assert_eq!("#FFFFFFFF", rgba_color((1.0, 1.0, 1.0, 1.0)));
     }
+    #[test]
+    fn escape_markup() {
+        let text = Text::new(
+            arr1(&[0.0, 0.0]),
+            0.0,
+            String::from("<R1 & C1>"),
+            (0.0, 0.0, 0.0, 1.0),
+            1.27,
+            "osifont",
+            Vec::new(),
+            false,
+        );
+        assert!(text_markup(&text).contains(">&lt;R1 &amp; C1&gt;</span>"));
+    }
+    #[test]
+    fn arc_from_points() {
+        let (center, radius) =
+            arc_center(&arr1(&[1.0, 0.0]), &arr1(&[0.0, 1.0]), &arr1(&[-1.0, 0.0])).unwrap();
+        assert!(center[0].abs() < 1e-9 && center[1].abs() < 1e-9);
+        assert!((radius - 1.0).abs() < 1e-9);
+        assert_eq!(
+            None,
+            arc_center(&arr1(&[0.0, 0.0]), &arr1(&[1.0, 1.0]), &arr1(&[2.0, 2.0])).map(|c| c.1)
+        );
+    }
+    #[test]
+    fn arc_extreme_points() {
+        // the upper half circle through the top.
+        let outline = arr_outline(&arc_outline(
+            &arr1(&[1.0, 0.0]),
+            &arr1(&[0.0, 1.0]),
+            &arr1(&[-1.0, 0.0]),
+        ));
+        assert!((outline[[1, 1]] - 1.0).abs() < 1e-9);
+        assert!(outline[[0, 1]].abs() < 1e-9);
+        // the same points the other way round cover the lower half circle.
+        let outline = arr_outline(&arc_outline(
+            &arr1(&[1.0, 0.0]),
+            &arr1(&[0.0, -1.0]),
+            &arr1(&[-1.0, 0.0]),
+        ));
+        assert!((outline[[0, 1]] + 1.0).abs() < 1e-9);
+        assert!(outline[[1, 1]].abs() < 1e-9);
+        // a small arc has no extreme points.
+        let point = |a: f64| arr1(&[a.cos(), a.sin()]);
+        assert_eq!(
+            3,
+            arc_outline(&point(0.7), &point(0.8), &point(0.9)).nrows()
+        );
+    }
+    #[test]
+    fn paper_size() {
+        assert_eq!(Some(paper::A3), paper::size("A3"));
+        assert_eq!(Some((210.0, 297.0)), paper::size("A4 portrait"));
+        assert_eq!(Some(paper::US_LEGAL), paper::size("(paper \"USLegal\")"));
+        assert_eq!(Some((200.0, 150.0)), paper::size("User 200 150"));
+        assert_eq!(None, paper::size("A7"));
+        assert_eq!(
+            Some(("USLetter", paper::US_LETTER)),
+            paper::parse("USLetter")
+        );
+        assert_eq!(Some(("A", paper::ANSI_A)), paper::parse("A"));
+        assert_eq!("A4", paper::name((210.0, 297.0)));
+        assert_eq!("USLedger", paper::name(paper::ANSI_B));
+        assert_eq!("User", paper::name((200.0, 150.0)));
+    }
 }
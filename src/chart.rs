@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+
+use ndarray::{arr1, arr2, Array2};
+
+use crate::border::LegendEntry;
+use crate::cairo_plotter::{
+    points, text_width, Line, LineCap, PlotItem, Polyline, Rectangle, Text,
+};
+use crate::error::Error;
+use crate::nets::PALETTE;
+use crate::simulation::format_si;
+use crate::text;
+use crate::theme::Theme;
+
+/// space for the tick labels left of the plot area.
+const MARGIN_LEFT: f64 = 20.0;
+const MARGIN_RIGHT: f64 = 5.0;
+/// space for the title above the plot area.
+const MARGIN_TOP: f64 = 8.0;
+/// space for the tick labels and the axis label below the plot area.
+const MARGIN_BOTTOM: f64 = 12.0;
+/// the number of ticks on a linear axis.
+const TICKS: usize = 8;
+const TICK_LENGTH: f64 = 1.5;
+const GRID_COLOR: (f64, f64, f64, f64) = (0.8, 0.8, 0.8, 1.0);
+const TRACE_WIDTH: f64 = 0.3;
+const LEGEND_LINE: f64 = 4.0;
+
+/// The kind of the SPICE analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Analysis {
+    Transient,
+    Dc,
+    Ac,
+}
+
+/// An axis of the chart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Axis {
+    pub label: String,
+    /// the unit for the tick labels, the SI prefix is added.
+    pub unit: String,
+    /// logarithmic axis with the ticks at the decades.
+    pub log: bool,
+    /// the range of the axis, taken from the data when `None`.
+    pub range: Option<(f64, f64)>,
+}
+
+impl Axis {
+    pub fn new(label: &str, unit: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            unit: unit.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A trace of the chart, the color is taken from the palette when `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub name: String,
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub color: Option<(f64, f64, f64, f64)>,
+}
+
+impl Trace {
+    pub fn new(name: &str, x: Vec<f64>, y: Vec<f64>) -> Self {
+        Self {
+            name: name.to_string(),
+            x,
+            y,
+            color: None,
+        }
+    }
+}
+
+/// A line chart with axes, grid and legend.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chart {
+    pub title: String,
+    pub x: Axis,
+    pub y: Axis,
+    pub traces: Vec<Trace>,
+}
+
+impl Chart {
+    pub fn new(title: &str, x: Axis, y: Axis) -> Self {
+        Self {
+            title: title.to_string(),
+            x,
+            y,
+            traces: Vec::new(),
+        }
+    }
+    /// create the chart for the analysis from the simulation vectors.
+    ///
+    /// The x values are the `time`, `frequency` or the `*-sweep` vector,
+    /// the traces are the vectors with the names. The AC vectors are complex,
+    /// the caller must supply the magnitudes.
+    pub fn analysis(
+        analysis: Analysis,
+        vectors: &HashMap<String, Vec<f64>>,
+        names: &[&str],
+    ) -> Result<Self, Error> {
+        let (title, x_name, x) = match analysis {
+            Analysis::Transient => ("Transient", String::from("time"), Axis::new("time", "s")),
+            Analysis::Ac => {
+                let mut axis = Axis::new("frequency", "Hz");
+                axis.log = true;
+                ("AC", String::from("frequency"), axis)
+            }
+            Analysis::Dc => {
+                let name = vectors
+                    .keys()
+                    .find(|k| k.ends_with("-sweep"))
+                    .cloned()
+                    .unwrap_or_else(|| String::from("v-sweep"));
+                let unit = if name.starts_with("i-") { "A" } else { "V" };
+                ("DC", name.clone(), Axis::new(&name, unit))
+            }
+        };
+        let get = |name: &str| {
+            vectors
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::VectorNotFound(name.to_string()))
+        };
+        let xs = get(&x_name)?;
+        let currents = names
+            .iter()
+            .all(|n| n.contains("#branch") || n.starts_with("i("));
+        let unit = if !names.is_empty() && currents {
+            "A"
+        } else {
+            "V"
+        };
+        let mut chart = Chart::new(title, x, Axis::new("", unit));
+        for name in names {
+            chart.traces.push(Trace::new(name, xs.clone(), get(name)?));
+        }
+        Ok(chart)
+    }
+    /// get the range of the axis from the data.
+    pub(crate) fn range(&self, axis: &Axis, x: bool) -> (f64, f64) {
+        if let Some(range) = axis.range {
+            return range;
+        }
+        let values = self
+            .traces
+            .iter()
+            .flat_map(|t| if x { t.x.iter() } else { t.y.iter() })
+            .filter(|v| v.is_finite() && (!axis.log || **v > 0.0));
+        let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(*v), max.max(*v))
+        });
+        if min > max {
+            (
+                if axis.log { 1.0 } else { 0.0 },
+                if axis.log { 10.0 } else { 1.0 },
+            )
+        } else if axis.log {
+            (
+                10f64.powf(min.log10().floor()),
+                10f64.powf(max.log10().ceil().max(min.log10().floor() + 1.0)),
+            )
+        } else if min == max {
+            (min - min.abs().max(1.0), max + max.abs().max(1.0))
+        } else {
+            let (start, end, _) = nice(min, max, TICKS);
+            (start, end)
+        }
+    }
+    /// get the plot area inside the chart area.
+    fn inner(area: ((f64, f64), (f64, f64))) -> ((f64, f64), (f64, f64)) {
+        (
+            (area.0 .0 + MARGIN_LEFT, area.0 .1 + MARGIN_TOP),
+            (area.1 .0 - MARGIN_RIGHT, area.1 .1 - MARGIN_BOTTOM),
+        )
+    }
+    /// map the data point to the position in the chart area.
+    pub(crate) fn map(&self, area: ((f64, f64), (f64, f64)), x: f64, y: f64) -> (f64, f64) {
+        let ranges = (self.range(&self.x, true), self.range(&self.y, false));
+        self.scale(area, ranges, x, y)
+    }
+    /// map the data point with the ranges of the axes.
+    fn scale(
+        &self,
+        area: ((f64, f64), (f64, f64)),
+        ranges: ((f64, f64), (f64, f64)),
+        x: f64,
+        y: f64,
+    ) -> (f64, f64) {
+        let ((x0, y0), (x1, y1)) = Chart::inner(area);
+        let scale = |axis: &Axis, range: (f64, f64), v: f64| {
+            if axis.log {
+                (v.log10() - range.0.log10()) / (range.1.log10() - range.0.log10())
+            } else {
+                (v - range.0) / (range.1 - range.0)
+            }
+        };
+        let fx = scale(&self.x, ranges.0, x).clamp(0.0, 1.0);
+        let fy = scale(&self.y, ranges.1, y).clamp(0.0, 1.0);
+        (x0 + fx * (x1 - x0), y1 - fy * (y1 - y0))
+    }
+    /// plot the chart into the area of the page.
+    pub fn plot(
+        &self,
+        area: ((f64, f64), (f64, f64)),
+        theme: &Theme,
+    ) -> Result<Vec<PlotItem>, Error> {
+        let mut items: Vec<PlotItem> = Vec::new();
+        let stroke = theme.stroke("border_stroke")?;
+        let mut effects = theme.effects("border_effects")?;
+        let ((x0, y0), (x1, y1)) = Chart::inner(area);
+        let x_range = check_range(&self.x, self.range(&self.x, true))?;
+        let y_range = check_range(&self.y, self.range(&self.y, false))?;
+
+        // grid and tick labels.
+        let x_ticks = axis_ticks(&self.x, x_range);
+        let y_ticks = axis_ticks(&self.y, y_range);
+        effects.justify = vec![String::from("center"), String::from("top")];
+        for tick in &x_ticks {
+            let (x, _) = self.scale(area, (x_range, y_range), *tick, y_range.0);
+            items.push(grid(arr2(&[[x, y0], [x, y1]])));
+            items.push(tick_line(
+                arr2(&[[x, y1], [x, y1 + TICK_LENGTH]]),
+                stroke.width,
+                stroke.color,
+            ));
+            items.push(text!(
+                arr1(&[x, y1 + TICK_LENGTH + 0.5]),
+                0.0,
+                format_si(*tick, &self.x.unit, 3),
+                effects
+            ));
+        }
+        effects.justify = vec![String::from("right")];
+        for tick in &y_ticks {
+            let (_, y) = self.scale(area, (x_range, y_range), x_range.0, *tick);
+            items.push(grid(arr2(&[[x0, y], [x1, y]])));
+            items.push(tick_line(
+                arr2(&[[x0 - TICK_LENGTH, y], [x0, y]]),
+                stroke.width,
+                stroke.color,
+            ));
+            items.push(text!(
+                arr1(&[x0 - TICK_LENGTH - 0.5, y]),
+                0.0,
+                format_si(*tick, &self.y.unit, 3),
+                effects
+            ));
+        }
+        items.push(PlotItem::Rectangle(
+            10,
+            Rectangle::new(
+                arr2(&[[x0, y0], [x1, y1]]),
+                stroke.color,
+                stroke.width,
+                stroke.linetype.clone(),
+                None,
+            ),
+        ));
+
+        // title and axis labels.
+        effects.justify = vec![String::from("center"), String::from("bottom")];
+        if !self.title.is_empty() {
+            items.push(text!(
+                arr1(&[(x0 + x1) / 2.0, y0 - 2.0]),
+                0.0,
+                self.title.clone(),
+                effects
+            ));
+        }
+        if !self.x.label.is_empty() {
+            items.push(text!(
+                arr1(&[(x0 + x1) / 2.0, area.1 .1 - 1.0]),
+                0.0,
+                self.x.label.clone(),
+                effects
+            ));
+        }
+        if !self.y.label.is_empty() {
+            effects.justify = vec![String::from("center"), String::from("top")];
+            items.push(text!(
+                arr1(&[area.0 .0 + 1.0, (y0 + y1) / 2.0]),
+                90.0,
+                self.y.label.clone(),
+                effects
+            ));
+        }
+
+        // the traces, points outside of a log axis are skipped.
+        let mut legend: Vec<LegendEntry> = Vec::new();
+        for (i, trace) in self.traces.iter().enumerate() {
+            let color = trace.color.unwrap_or(PALETTE[i % PALETTE.len()]);
+            let pts: Vec<(f64, f64)> = trace
+                .x
+                .iter()
+                .zip(trace.y.iter())
+                .filter(|(x, y)| {
+                    x.is_finite()
+                        && y.is_finite()
+                        && (!self.x.log || **x > 0.0)
+                        && (!self.y.log || **y > 0.0)
+                })
+                .map(|(x, y)| self.scale(area, (x_range, y_range), *x, *y))
+                .collect();
+            if pts.len() > 1 {
+                items.push(PlotItem::Polyline(
+                    10,
+                    Polyline::new(
+                        points(&pts),
+                        color,
+                        TRACE_WIDTH,
+                        String::from("default"),
+                        None,
+                    ),
+                ));
+            }
+            legend.push((trace.name.clone(), color));
+        }
+
+        // the legend in the top right corner of the plot area.
+        if !legend.is_empty() {
+            effects.justify = vec![String::from("left")];
+            let width = legend
+                .iter()
+                .map(|(text, _)| text_width(text, effects.font_size.0))
+                .fold(0.0, f64::max)
+                + LEGEND_LINE
+                + 4.0;
+            let origin = (x1 - width - 1.0, y0 + 1.0);
+            items.push(PlotItem::Rectangle(
+                99,
+                Rectangle::new(
+                    arr2(&[
+                        [origin.0, origin.1],
+                        [
+                            origin.0 + width,
+                            origin.1 + legend.len() as f64 * LEGEND_LINE + 1.0,
+                        ],
+                    ]),
+                    stroke.color,
+                    stroke.width,
+                    stroke.linetype.clone(),
+                    Some((1.0, 1.0, 1.0, 0.8)),
+                ),
+            ));
+            for (i, (name, color)) in legend.into_iter().enumerate() {
+                let y = origin.1 + 0.5 + (i as f64 + 0.5) * LEGEND_LINE;
+                items.push(tick_line(
+                    arr2(&[[origin.0 + 1.0, y], [origin.0 + 1.0 + LEGEND_LINE, y]]),
+                    TRACE_WIDTH * 2.0,
+                    color,
+                ));
+                items.push(text!(
+                    arr1(&[origin.0 + LEGEND_LINE + 2.0, y]),
+                    0.0,
+                    name,
+                    effects
+                ));
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn grid(pts: Array2<f64>) -> PlotItem {
+    PlotItem::Line(
+        1,
+        Line::new(pts, 0.1, String::from("default"), LineCap::Butt, GRID_COLOR),
+    )
+}
+
+fn tick_line(pts: Array2<f64>, width: f64, color: (f64, f64, f64, f64)) -> PlotItem {
+    PlotItem::Line(
+        10,
+        Line::new(pts, width, String::from("default"), LineCap::Butt, color),
+    )
+}
+
+/// get a range with round limits and the step for about `count` ticks.
+pub(crate) fn nice(min: f64, max: f64, count: usize) -> (f64, f64, f64) {
+    let raw = (max - min) / count.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|f| f * magnitude)
+        .find(|s| *s >= raw * (1.0 - 1e-9))
+        .unwrap_or(10.0 * magnitude);
+    (
+        (min / step + 1e-9).floor() * step,
+        (max / step - 1e-9).ceil() * step,
+        step,
+    )
+}
+
+/// the range must be ordered and positive on a log axis.
+fn check_range(axis: &Axis, range: (f64, f64)) -> Result<(f64, f64), Error> {
+    if range.0.is_finite()
+        && range.1.is_finite()
+        && range.0 < range.1
+        && (!axis.log || range.0 > 0.0)
+    {
+        Ok(range)
+    } else {
+        Err(Error::AxisRange(axis.label.clone(), range.0, range.1))
+    }
+}
+
+/// get the ticks of the axis, the decades for a log axis.
+fn axis_ticks(axis: &Axis, range: (f64, f64)) -> Vec<f64> {
+    if axis.log {
+        let (start, end) = (
+            range.0.log10().ceil() as i32,
+            range.1.log10().floor() as i32,
+        );
+        (start..=end).map(|e| 10f64.powi(e)).collect()
+    } else {
+        let (_, _, step) = nice(range.0, range.1, TICKS);
+        let start = (range.0 / step - 1e-9).ceil() as i64;
+        let end = (range.1 / step + 1e-9).floor() as i64;
+        (start..=end).map(|i| i as f64 * step).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{axis_ticks, check_range, nice, Analysis, Axis, Chart};
+    use crate::error::Error;
+
+    #[test]
+    fn ticks() {
+        assert_eq!((0.0, 10.0, 2.0), nice(0.3, 9.2, 5));
+        assert_eq!((-1.0, 1.0, 0.5), nice(-0.9, 0.8, 4));
+        let mut axis = Axis::new("time", "s");
+        let ticks = axis_ticks(&axis, (0.0, 1.0));
+        assert_eq!(6, ticks.len());
+        assert_eq!(1.0, ticks[5]);
+        axis.log = true;
+        assert_eq!(vec![10.0, 100.0, 1000.0], axis_ticks(&axis, (10.0, 1000.0)));
+        assert!(matches!(
+            check_range(&axis, (0.0, 1000.0)),
+            Err(Error::AxisRange(..))
+        ));
+        assert!(matches!(
+            check_range(&axis, (100.0, 10.0)),
+            Err(Error::AxisRange(..))
+        ));
+        axis.log = false;
+        assert!(check_range(&axis, (-1.0, 1.0)).is_ok());
+        assert!(matches!(
+            check_range(&axis, (1.0, 1.0)),
+            Err(Error::AxisRange(..))
+        ));
+    }
+    #[test]
+    fn analysis() {
+        let vectors = HashMap::from([
+            (String::from("time"), vec![0.0, 0.001, 0.002]),
+            (String::from("out"), vec![0.0, 1.0, 0.5]),
+        ]);
+        let chart = Chart::analysis(Analysis::Transient, &vectors, &["out"]).unwrap();
+        assert_eq!("s", chart.x.unit);
+        assert_eq!(1, chart.traces.len());
+        let (min, max) = chart.range(&chart.x, true);
+        assert_eq!(0.0, min);
+        assert!((max - 0.002).abs() < 1e-12);
+        let area = ((0.0, 0.0), (125.0, 120.0));
+        assert_eq!((20.0, 108.0), chart.map(area, 0.0, 0.0));
+        assert!(matches!(
+            Chart::analysis(Analysis::Ac, &vectors, &["out"]),
+            Err(Error::VectorNotFound(_))
+        ));
+    }
+}
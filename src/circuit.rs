@@ -0,0 +1,561 @@
+use ndarray::{arr1, arr2, Array2};
+
+use crate::cairo_plotter::{Arc, Circle, Line, LineCap, PlotItem, Polyline, Rectangle, Text};
+use crate::error::Error;
+use crate::text;
+use crate::theme::Theme;
+use elektron_sexp::{Effects, Stroke};
+use elektron_spice::Circuit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const GRID: f64 = 2.54;
+const MARGIN: f64 = 10.0;
+/// space for the net names left of the rails.
+const LABEL_WIDTH: f64 = 20.0;
+/// distance between the net rails.
+const RAIL_PITCH: f64 = 5.08;
+/// distance between the pins of a multi pin symbol.
+const PIN_PITCH: f64 = 5.08;
+/// the length of a two pin symbol from pin to pin.
+const SYMBOL_LENGTH: f64 = 15.24;
+/// the column of a two pin symbol, the lower pin is routed right of the text.
+const COLUMN_WIDTH: f64 = 20.32;
+const CHANNEL: f64 = 15.24;
+const JUNCTION_RADIUS: f64 = 0.35;
+
+/// The generic symbols of the components.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Resistor,
+    Capacitor,
+    Inductor,
+    Diode,
+    Transistor,
+    Voltage,
+    Current,
+    Subcircuit,
+}
+
+/// A component of the circuit with the nodes of its pins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub kind: Kind,
+    pub reference: String,
+    pub value: String,
+    pub nodes: Vec<String>,
+}
+
+impl Component {
+    /// create the component, the kind is taken from the SPICE reference prefix.
+    pub fn new(reference: &str, nodes: Vec<String>, value: &str) -> Self {
+        let kind = match reference.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some('R') => Kind::Resistor,
+            Some('C') => Kind::Capacitor,
+            Some('L') => Kind::Inductor,
+            Some('D') => Kind::Diode,
+            Some('Q') | Some('J') | Some('M') => Kind::Transistor,
+            Some('V') => Kind::Voltage,
+            Some('I') => Kind::Current,
+            _ => Kind::Subcircuit,
+        };
+        Self {
+            kind,
+            reference: reference.to_string(),
+            value: value.to_string(),
+            nodes,
+        }
+    }
+    /// the names of the pins drawn in the multi pin symbols.
+    fn pin_names(&self) -> Vec<String> {
+        if self.kind == Kind::Transistor && self.nodes.len() == 3 {
+            vec![String::from("C"), String::from("B"), String::from("E")]
+        } else {
+            (1..=self.nodes.len()).map(|i| i.to_string()).collect()
+        }
+    }
+    fn two_pins(&self) -> bool {
+        self.nodes.len() == 2 && self.kind != Kind::Transistor && self.kind != Kind::Subcircuit
+    }
+}
+
+/// get the components of the circuit from its SPICE netlist.
+///
+/// The items of the circuit are private, the netlist is written with
+/// `Circuit::save` to a temporary file and parsed. A `Circuit` has no
+/// inductors or current sources, use `parse` for netlists with them.
+pub fn components(circuit: &Circuit) -> Result<Vec<Component>, Error> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "elektron-circuit-{}-{}.cir",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let saved = circuit
+        .save(Some(path.to_string_lossy().to_string()))
+        .map_err(|err| Error::IoError(err.to_string()));
+    let netlist = saved.and_then(|_| Ok(std::fs::read_to_string(&path)?));
+    let _ = std::fs::remove_file(&path);
+    Ok(parse(&netlist?))
+}
+
+/// get the components of a SPICE netlist, subcircuit definitions and controls are skipped.
+pub fn parse(netlist: &str) -> Vec<Component> {
+    let mut components = Vec::new();
+    let mut subckt = 0;
+    for line in netlist.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let first = match tokens.first() {
+            Some(first) => first.to_ascii_lowercase(),
+            None => continue,
+        };
+        if first == ".subckt" {
+            subckt += 1;
+        } else if first == ".ends" {
+            subckt -= 1;
+        }
+        if subckt > 0 || first.starts_with(['.', '*', '+']) {
+            continue;
+        }
+        // the number of nodes by the element type.
+        let nodes = match first.chars().next() {
+            Some('r' | 'c' | 'l' | 'd' | 'v' | 'i') => 2,
+            Some('q' | 'j') => 3,
+            Some('m') => 4,
+            Some('x') => tokens.len().saturating_sub(2),
+            _ => continue,
+        };
+        if tokens.len() < nodes + 2 {
+            continue;
+        }
+        // `Circuit` always adds the prefix to transistors, sources and subcircuits.
+        let mut reference = tokens[0];
+        let mut chars = reference.chars();
+        if matches!(
+            (chars.next(), chars.next()),
+            (Some(a), Some(b)) if a == b && "QVXqvx".contains(a)
+        ) {
+            reference = &reference[1..];
+        }
+        components.push(Component::new(
+            reference,
+            tokens[1..=nodes].iter().map(|n| n.to_string()).collect(),
+            &tokens[nodes + 1..].join(" "),
+        ));
+    }
+    components
+}
+
+fn is_ground(node: &str) -> bool {
+    node == "0" || node.eq_ignore_ascii_case("gnd")
+}
+
+/// Places the components in a row and routes the nets on rails above them.
+///
+/// Every net gets a horizontal rail with the net name on the left. The pins are
+/// connected to the rails with vertical wires, lower pins of the two pin symbols
+/// on ground get a ground symbol.
+struct CircuitLayout {
+    items: Vec<PlotItem>,
+    wire: Stroke,
+    symbol: Stroke,
+    effects: Effects,
+    rails: Vec<(String, f64)>,
+    /// the connection points on the rails.
+    taps: Vec<(String, f64)>,
+    top: f64,
+}
+
+impl CircuitLayout {
+    fn rail(&self, node: &str) -> f64 {
+        self.rails
+            .iter()
+            .find(|(name, _)| name == node)
+            .map(|(_, y)| *y)
+            .unwrap_or(MARGIN)
+    }
+    fn line(&mut self, pts: Array2<f64>, stroke: &Stroke) {
+        self.items.push(PlotItem::Line(
+            10,
+            Line::new(
+                pts,
+                stroke.width,
+                stroke.linetype.clone(),
+                LineCap::Butt,
+                stroke.color,
+            ),
+        ));
+    }
+    fn polyline(&mut self, pts: Array2<f64>, fill: bool) {
+        let fill = if fill { Some(self.symbol.color) } else { None };
+        self.items.push(PlotItem::Polyline(
+            10,
+            Polyline::new(
+                pts,
+                self.symbol.color,
+                self.symbol.width,
+                self.symbol.linetype.clone(),
+                fill,
+            ),
+        ));
+    }
+    fn text(&mut self, x: f64, y: f64, text: &str, justify: &[&str]) {
+        let mut effects = self.effects.clone();
+        effects.justify = justify.iter().map(|j| j.to_string()).collect();
+        self.items
+            .push(text!(arr1(&[x, y]), 0.0, text.to_string(), effects));
+    }
+    /// connect the pin to the rail of the node.
+    fn tap(&mut self, x: f64, y: f64, node: &str) {
+        let rail = self.rail(node);
+        let wire = self.wire.clone();
+        self.line(arr2(&[[x, y], [x, rail]]), &wire);
+        self.taps.push((node.to_string(), x));
+    }
+    fn ground(&mut self, x: f64, y: f64) {
+        let symbol = self.symbol.clone();
+        self.line(arr2(&[[x, y], [x, y + GRID / 2.0]]), &symbol);
+        self.polyline(
+            arr2(&[
+                [x - GRID / 2.0, y + GRID / 2.0],
+                [x + GRID / 2.0, y + GRID / 2.0],
+                [x, y + GRID],
+                [x - GRID / 2.0, y + GRID / 2.0],
+            ]),
+            false,
+        );
+    }
+    /// draw the vertical two pin symbol with the upper pin at x.
+    fn two_pins(&mut self, component: &Component, x: f64) {
+        let (top, bottom) = (self.top, self.top + SYMBOL_LENGTH);
+        let center = self.top + SYMBOL_LENGTH / 2.0;
+        let symbol = self.symbol.clone();
+        // the half length of the symbol body.
+        let body = match component.kind {
+            Kind::Capacitor => GRID / 4.0,
+            Kind::Diode => GRID / 2.0,
+            Kind::Voltage | Kind::Current => GRID,
+            _ => 1.5 * GRID,
+        };
+        self.line(arr2(&[[x, top], [x, center - body]]), &symbol);
+        self.line(arr2(&[[x, center + body], [x, bottom]]), &symbol);
+        match component.kind {
+            Kind::Resistor => self.items.push(PlotItem::Rectangle(
+                10,
+                Rectangle::new(
+                    arr2(&[
+                        [x - 0.4 * GRID, center - body],
+                        [x + 0.4 * GRID, center + body],
+                    ]),
+                    symbol.color,
+                    symbol.width,
+                    symbol.linetype.clone(),
+                    None,
+                ),
+            )),
+            Kind::Capacitor => {
+                for y in [center - body, center + body] {
+                    self.line(arr2(&[[x - 0.8 * GRID, y], [x + 0.8 * GRID, y]]), &symbol);
+                }
+            }
+            Kind::Inductor => {
+                let turn = 2.0 * body / 4.0;
+                for i in 0..4 {
+                    let y = center - body + i as f64 * turn;
+                    self.items.push(PlotItem::Arc(
+                        10,
+                        Arc::new(
+                            arr1(&[x, y]),
+                            arr1(&[x + turn / 2.0, y + turn / 2.0]),
+                            arr1(&[x, y + turn]),
+                            symbol.width,
+                            symbol.linetype.clone(),
+                            symbol.color,
+                            None,
+                        ),
+                    ));
+                }
+            }
+            Kind::Diode => {
+                self.polyline(
+                    arr2(&[
+                        [x - body, center - body],
+                        [x + body, center - body],
+                        [x, center + body],
+                        [x - body, center - body],
+                    ]),
+                    true,
+                );
+                self.line(
+                    arr2(&[[x - body, center + body], [x + body, center + body]]),
+                    &symbol,
+                );
+            }
+            Kind::Voltage | Kind::Current => {
+                self.items.push(PlotItem::Circle(
+                    10,
+                    Circle::new(
+                        arr1(&[x, center]),
+                        body,
+                        symbol.width,
+                        symbol.linetype.clone(),
+                        symbol.color,
+                        None,
+                    ),
+                ));
+                if component.kind == Kind::Voltage {
+                    let (plus, minus) = (center - body / 2.0, center + body / 2.0);
+                    self.line(
+                        arr2(&[[x - 0.3 * GRID, plus], [x + 0.3 * GRID, plus]]),
+                        &symbol,
+                    );
+                    self.line(
+                        arr2(&[[x, plus - 0.3 * GRID], [x, plus + 0.3 * GRID]]),
+                        &symbol,
+                    );
+                    self.line(
+                        arr2(&[[x - 0.3 * GRID, minus], [x + 0.3 * GRID, minus]]),
+                        &symbol,
+                    );
+                } else {
+                    self.line(
+                        arr2(&[[x, center - 0.6 * GRID], [x, center + 0.6 * GRID]]),
+                        &symbol,
+                    );
+                    self.polyline(
+                        arr2(&[
+                            [x - 0.3 * GRID, center + 0.2 * GRID],
+                            [x, center + 0.6 * GRID],
+                            [x + 0.3 * GRID, center + 0.2 * GRID],
+                        ]),
+                        false,
+                    );
+                }
+            }
+            _ => {}
+        }
+        self.text(
+            x + GRID,
+            center - GRID / 2.0,
+            &component.reference,
+            &["left"],
+        );
+        self.text(x + GRID, center + GRID / 2.0, &component.value, &["left"]);
+
+        self.tap(x, top, &component.nodes[0]);
+        if is_ground(&component.nodes[1]) {
+            self.ground(x, bottom);
+        } else {
+            let wire = self.wire.clone();
+            let channel = x + CHANNEL;
+            self.line(
+                arr2(&[
+                    [x, bottom],
+                    [x, bottom + GRID],
+                    [channel, bottom + GRID],
+                    [channel, bottom],
+                ]),
+                &wire,
+            );
+            self.tap(channel, bottom, &component.nodes[1]);
+        }
+    }
+    /// draw the box symbol with the pins on the upper edge.
+    fn multi_pins(&mut self, component: &Component, x: f64) {
+        let width = (component.nodes.len().max(1) - 1) as f64 * PIN_PITCH;
+        let (top, bottom) = (self.top + GRID, self.top + 4.0 * GRID);
+        let symbol = self.symbol.clone();
+        self.items.push(PlotItem::Rectangle(
+            10,
+            Rectangle::new(
+                arr2(&[[x - GRID, top], [x + width + GRID, bottom]]),
+                symbol.color,
+                symbol.width,
+                symbol.linetype.clone(),
+                None,
+            ),
+        ));
+        for (i, (node, name)) in component
+            .nodes
+            .iter()
+            .zip(component.pin_names())
+            .enumerate()
+        {
+            let px = x + i as f64 * PIN_PITCH;
+            self.line(arr2(&[[px, self.top], [px, top]]), &symbol);
+            self.text(px, top + GRID / 2.0, &name, &["center", "top"]);
+            self.tap(px, self.top, node);
+        }
+        let center = x + width / 2.0;
+        self.text(
+            center,
+            bottom + GRID / 2.0,
+            &component.reference,
+            &["center", "top"],
+        );
+        self.text(
+            center,
+            bottom + 1.5 * GRID,
+            &component.value,
+            &["center", "top"],
+        );
+    }
+}
+
+/// place the components, returns the plot items and the size of the drawing.
+pub fn layout(
+    components: &[Component],
+    theme: &Theme,
+) -> Result<(Vec<PlotItem>, (f64, f64)), Error> {
+    // the nets with a rail in the order of the first use.
+    let mut rails: Vec<(String, f64)> = Vec::new();
+    for component in components {
+        for (i, node) in component.nodes.iter().enumerate() {
+            let grounded = component.two_pins() && i == 1 && is_ground(node);
+            if !grounded && !rails.iter().any(|(name, _)| name == node) {
+                rails.push((node.clone(), MARGIN + rails.len() as f64 * RAIL_PITCH));
+            }
+        }
+    }
+    let mut layout = CircuitLayout {
+        items: Vec::new(),
+        wire: theme.stroke("wire")?,
+        symbol: theme.stroke("symbol")?,
+        effects: theme.effects("property")?,
+        top: MARGIN + rails.len() as f64 * RAIL_PITCH + RAIL_PITCH,
+        rails,
+        taps: Vec::new(),
+    };
+    let start = MARGIN + LABEL_WIDTH + 2.0 * GRID;
+    let mut x = start;
+    for component in components {
+        if component.two_pins() {
+            layout.two_pins(component, x);
+            x += COLUMN_WIDTH;
+        } else {
+            layout.multi_pins(component, x);
+            x += component.nodes.len() as f64 * PIN_PITCH + 2.0 * GRID;
+        }
+    }
+    // the rails from the net name to the last tap, with junctions at the inner taps.
+    let rail_start = MARGIN + LABEL_WIDTH;
+    let wire = layout.wire.clone();
+    for (name, y) in layout.rails.clone() {
+        let taps: Vec<f64> = layout
+            .taps
+            .iter()
+            .filter(|(node, _)| *node == name)
+            .map(|(_, x)| *x)
+            .collect();
+        let end = taps.iter().copied().fold(rail_start, f64::max);
+        layout.line(arr2(&[[rail_start, y], [end, y]]), &wire);
+        layout.text(rail_start - GRID / 2.0, y, &name, &["right"]);
+        for tap in taps.iter().filter(|tap| **tap < end) {
+            layout.items.push(PlotItem::Circle(
+                10,
+                Circle::new(
+                    arr1(&[*tap, y]),
+                    JUNCTION_RADIUS,
+                    wire.width,
+                    wire.linetype.clone(),
+                    wire.color,
+                    Some(wire.color),
+                ),
+            ));
+        }
+    }
+    let size = (x + MARGIN, layout.top + SYMBOL_LENGTH + 3.0 * GRID + MARGIN);
+    Ok((layout.items, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{components, layout, parse, Component, Kind};
+    use crate::cairo_plotter::PlotItem;
+    use crate::theme::Theme;
+    use elektron_spice::Circuit;
+
+    #[test]
+    fn component_kinds() {
+        let r = Component::new("R1", vec![String::from("in"), String::from("out")], "10k");
+        assert_eq!(Kind::Resistor, r.kind);
+        assert!(r.two_pins());
+        let q = Component::new(
+            "Q1",
+            vec![String::from("c"), String::from("b"), String::from("0")],
+            "BC547",
+        );
+        assert_eq!(Kind::Transistor, q.kind);
+        assert!(!q.two_pins());
+        assert_eq!(vec!["C", "B", "E"], q.pin_names());
+        assert_eq!(
+            Kind::Subcircuit,
+            Component::new("XU1", Vec::new(), "TL072").kind
+        );
+    }
+    #[test]
+    fn divider() {
+        let components = vec![
+            Component::new("V1", vec![String::from("in"), String::from("0")], "5V"),
+            Component::new("R1", vec![String::from("in"), String::from("out")], "10k"),
+            Component::new("R2", vec![String::from("out"), String::from("0")], "10k"),
+        ];
+        let (items, size) = layout(&components, &Theme::kicad_2000()).unwrap();
+        // two rails, ground is drawn with symbols.
+        let texts: Vec<&str> = items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::Text(_, text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(texts.contains(&"in"));
+        assert!(texts.contains(&"out"));
+        assert!(!texts.contains(&"0"));
+        assert!(size.0 > 3.0 * 20.32);
+        // one junction on each rail, where V1 taps "in" and R1 taps "out" before the end.
+        let junctions = items
+            .iter()
+            .filter(|item| matches!(item, PlotItem::Circle(_, c) if c.fill.is_some()))
+            .count();
+        assert_eq!(2, junctions);
+    }
+    #[test]
+    fn netlist() {
+        let components = parse(
+            "* divider\n.subckt amp in out\nR9 in out 1k\n.ends\nL1 in out 10m\n\
+             I1 out 0 DC 1m\nXU1 in out 0 TL072\nQQ1 c b e BC547\n.end",
+        );
+        let references: Vec<&str> = components.iter().map(|c| c.reference.as_str()).collect();
+        assert_eq!(vec!["L1", "I1", "XU1", "Q1"], references);
+        assert_eq!(Kind::Inductor, components[0].kind);
+        assert_eq!("DC 1m", components[1].value);
+        assert_eq!(vec!["in", "out", "0"], components[2].nodes);
+        assert_eq!("TL072", components[2].value);
+    }
+    #[test]
+    fn circuit() {
+        let mut circuit = Circuit::new(String::from("divider"), Vec::new());
+        circuit.voltage(
+            String::from("1"),
+            String::from("in"),
+            String::from("0"),
+            String::from("5V"),
+        );
+        circuit.resistor(
+            String::from("R1"),
+            String::from("in"),
+            String::from("out"),
+            String::from("10k"),
+        );
+        circuit.resistor(
+            String::from("R2"),
+            String::from("out"),
+            String::from("0"),
+            String::from("10k"),
+        );
+        let components = components(&circuit).unwrap();
+        let references: Vec<&str> = components.iter().map(|c| c.reference.as_str()).collect();
+        assert_eq!(vec!["V1", "R1", "R2"], references);
+        assert_eq!(Kind::Voltage, components[0].kind);
+    }
+}
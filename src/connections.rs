@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use crate::cairo_plotter::text_width;
+use crate::error::Error;
+use crate::extra::Extra;
+use elektron_sexp::{Effects, LibrarySymbol, Schema, SchemaElement, Shape, Transform};
+
+/// points closer than this distance in mm are the same point.
+const TOLERANCE: f64 = 0.001;
+/// the symbol outline is the outline of the pins plus this margin.
+const SYMBOL_MARGIN: f64 = 1.27;
+/// the size of the area around a label position.
+const LABEL_SIZE: f64 = 2.54;
+/// distance between a wire and the tag.
+const TAG_GAP: f64 = 0.3;
+
+/// A wire from the start to the end point.
+pub type Wire = ((f64, f64), (f64, f64));
+/// A rectangle from the minimum to the maximum point.
+pub type Bounds = ((f64, f64), (f64, f64));
+
+/// The connection points of a page.
+///
+/// Used to find the missing junctions and the dangling wire ends and pins.
+#[derive(Debug, Default)]
+pub struct Connections {
+    wires: Vec<Wire>,
+    pins: Vec<(f64, f64)>,
+    /// the outlines of the symbols, estimated from the pin positions.
+    symbols: Vec<Bounds>,
+    junctions: Vec<(f64, f64)>,
+    /// labels, no connects, bus entries, sheet pins and hidden pins end a wire.
+    terminals: Vec<(f64, f64)>,
+    /// the outlines of the fields, labels and notes.
+    texts: Vec<Bounds>,
+}
+
+impl Connections {
+    pub fn new(
+        schema: &Schema,
+        page: usize,
+        library: &HashMap<String, LibrarySymbol>,
+        extra: &Extra,
+    ) -> Result<Self, Error> {
+        let mut connections = Connections {
+            terminals: extra.sheet_pins.clone(),
+            ..Default::default()
+        };
+        for element in schema.iter(page)? {
+            match element {
+                SchemaElement::Wire(wire) => {
+                    for i in 1..wire.pts.nrows() {
+                        connections.wires.push((
+                            (wire.pts[[i - 1, 0]], wire.pts[[i - 1, 1]]),
+                            (wire.pts[[i, 0]], wire.pts[[i, 1]]),
+                        ));
+                    }
+                }
+                SchemaElement::Junction(junction) => {
+                    connections.junctions.push((junction.at[0], junction.at[1]))
+                }
+                SchemaElement::NoConnect(no_connect) => connections
+                    .terminals
+                    .push((no_connect.at[0], no_connect.at[1])),
+                SchemaElement::Label(label) => {
+                    connections.terminals.push((label.at[0], label.at[1]));
+                    connections.texts.push(text_bounds(
+                        &label.text,
+                        (label.at[0], label.at[1]),
+                        label.angle,
+                        &label.effects,
+                    ));
+                }
+                SchemaElement::GlobalLabel(label) => {
+                    connections.terminals.push((label.at[0], label.at[1]));
+                    connections.texts.push(text_bounds(
+                        &label.text,
+                        (label.at[0], label.at[1]),
+                        label.angle,
+                        &label.effects,
+                    ));
+                }
+                SchemaElement::HierarchicalLabel(label) => {
+                    connections.terminals.push((label.at[0], label.at[1]));
+                    connections.texts.push(text_bounds(
+                        &label.text,
+                        (label.at[0], label.at[1]),
+                        label.angle,
+                        &label.effects,
+                    ));
+                }
+                SchemaElement::Text(text) => connections.texts.push(text_bounds(
+                    &text.text,
+                    (text.at[0], text.at[1]),
+                    text.angle,
+                    &text.effects,
+                )),
+                SchemaElement::BusEntry(bus) => {
+                    connections.terminals.push((bus.at[0], bus.at[1]));
+                    connections
+                        .terminals
+                        .push((bus.at[0] + bus.size[0], bus.at[1] + bus.size[1]));
+                }
+                SchemaElement::Symbol(symbol) if symbol.on_schema => {
+                    for property in &symbol.property {
+                        if let Some(effects) = &property.effects {
+                            if !effects.hide && !property.value.is_empty() {
+                                connections.texts.push(text_bounds(
+                                    &property.value,
+                                    (property.at[0], property.at[1]),
+                                    property.angle,
+                                    effects,
+                                ));
+                            }
+                        }
+                    }
+                    if let Some(lib) = schema
+                        .get_library(&symbol.lib_id)
+                        .or_else(|| library.get(&symbol.lib_id))
+                    {
+                        let mut pins: Vec<(f64, f64)> = Vec::new();
+                        for unit in &lib.symbols {
+                            if unit.unit == 0 || unit.unit == symbol.unit {
+                                for pin in &unit.pin {
+                                    let pos = Shape::transform(symbol, &pin.at);
+                                    pins.push((pos[0], pos[1]));
+                                    // hidden pins are connected by the name, they are never dangling.
+                                    if pin.hide {
+                                        connections.terminals.push((pos[0], pos[1]));
+                                    }
+                                }
+                            }
+                        }
+                        if !pins.is_empty() {
+                            let fold = |f: fn(f64, f64) -> f64, init: f64, x: bool| {
+                                pins.iter().map(|p| if x { p.0 } else { p.1 }).fold(init, f)
+                            };
+                            connections.symbols.push((
+                                (
+                                    fold(f64::min, f64::INFINITY, true) - SYMBOL_MARGIN,
+                                    fold(f64::min, f64::INFINITY, false) - SYMBOL_MARGIN,
+                                ),
+                                (
+                                    fold(f64::max, f64::NEG_INFINITY, true) + SYMBOL_MARGIN,
+                                    fold(f64::max, f64::NEG_INFINITY, false) + SYMBOL_MARGIN,
+                                ),
+                            ));
+                        }
+                        connections.pins.append(&mut pins);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(connections)
+    }
+
+    /// count the connections at the point, a wire passing the point counts twice.
+    fn count(&self, pt: (f64, f64)) -> usize {
+        let mut count = self.pins.iter().filter(|p| same(**p, pt)).count();
+        for (start, end) in &self.wires {
+            if same(*start, pt) || same(*end, pt) {
+                count += 1;
+            } else if on_wire(*start, *end, pt) {
+                count += 2;
+            }
+        }
+        count
+    }
+
+    /// get the points where three or more wires and pins meet without a junction.
+    pub fn missing_junctions(&self) -> Vec<(f64, f64)> {
+        let mut result: Vec<(f64, f64)> = Vec::new();
+        let candidates = self
+            .wires
+            .iter()
+            .flat_map(|(start, end)| [*start, *end])
+            .chain(self.pins.iter().copied());
+        for pt in candidates {
+            if self.count(pt) >= 3
+                && !self.junctions.iter().any(|j| same(*j, pt))
+                && !result.iter().any(|r| same(*r, pt))
+            {
+                result.push(pt);
+            }
+        }
+        result
+    }
+
+    /// get the wire ends which are not connected.
+    pub fn dangling_wires(&self) -> Vec<(f64, f64)> {
+        self.wires
+            .iter()
+            .flat_map(|(start, end)| [*start, *end])
+            .filter(|pt| self.count(*pt) == 1 && !self.terminals.iter().any(|t| same(*t, *pt)))
+            .collect()
+    }
+
+    /// get the pins which are not connected.
+    pub fn dangling_pins(&self) -> Vec<(f64, f64)> {
+        self.pins
+            .iter()
+            .copied()
+            .filter(|pt| self.count(*pt) == 1 && !self.terminals.iter().any(|t| same(*t, *pt)))
+            .collect()
+    }
+    /// get the connected wires, every segment is a list of wires.
+    pub fn segments(&self) -> Vec<Vec<Wire>> {
+        // union find over the wire indexes.
+        let mut parent: Vec<usize> = (0..self.wires.len()).collect();
+        fn root(parent: &mut [usize], i: usize) -> usize {
+            let mut i = i;
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        for a in 0..self.wires.len() {
+            for b in a + 1..self.wires.len() {
+                let (wa, wb) = (self.wires[a], self.wires[b]);
+                if on_wire(wa.0, wa.1, wb.0)
+                    || on_wire(wa.0, wa.1, wb.1)
+                    || on_wire(wb.0, wb.1, wa.0)
+                    || on_wire(wb.0, wb.1, wa.1)
+                {
+                    let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                    parent[ra] = rb;
+                }
+            }
+        }
+        let mut segments: Vec<(usize, Vec<Wire>)> = Vec::new();
+        for (i, wire) in self.wires.iter().enumerate() {
+            let r = root(&mut parent, i);
+            if let Some(segment) = segments.iter_mut().find(|(s, _)| *s == r) {
+                segment.1.push(*wire);
+            } else {
+                segments.push((r, vec![*wire]));
+            }
+        }
+        segments.into_iter().map(|(_, wires)| wires).collect()
+    }
+    /// get the wires of the segments which touch one of the points.
+    pub fn touching(&self, pts: &[(f64, f64)]) -> Vec<Wire> {
+        self.segments()
+            .into_iter()
+            .filter(|segment| {
+                segment
+                    .iter()
+                    .any(|(start, end)| pts.iter().any(|pt| on_wire(*start, *end, *pt)))
+            })
+            .flatten()
+            .collect()
+    }
+    /// get the areas of the symbols, labels and texts.
+    pub fn obstacles(&self) -> Vec<Bounds> {
+        let mut obstacles = self.symbols.clone();
+        obstacles.extend(self.texts.iter().copied());
+        for terminal in &self.terminals {
+            obstacles.push((
+                (terminal.0 - LABEL_SIZE, terminal.1 - LABEL_SIZE),
+                (terminal.0 + LABEL_SIZE, terminal.1 + LABEL_SIZE),
+            ));
+        }
+        obstacles
+    }
+}
+
+/// find a place for a tag of the size on the wires of the segment.
+///
+/// The tag is placed above horizontal and right of vertical wires. The longest
+/// wires are tried first, the tag must not overlap the obstacles. Returns the
+/// bounds of the tag, the first candidate is used when all places are taken.
+pub fn place_tag(segment: &[Wire], size: (f64, f64), obstacles: &[Bounds]) -> Option<Bounds> {
+    let mut wires: Vec<&Wire> = segment.iter().collect();
+    let length = |w: &Wire| ((w.1 .0 - w.0 .0).powi(2) + (w.1 .1 - w.0 .1).powi(2)).sqrt();
+    wires.sort_by(|a, b| length(b).total_cmp(&length(a)));
+    let mut first: Option<Bounds> = None;
+    for wire in wires {
+        for t in [0.5, 0.25, 0.75, 0.1, 0.9] {
+            let x = wire.0 .0 + (wire.1 .0 - wire.0 .0) * t;
+            let y = wire.0 .1 + (wire.1 .1 - wire.0 .1) * t;
+            let bounds = if (wire.1 .1 - wire.0 .1).abs() < TOLERANCE {
+                (
+                    (x - size.0 / 2.0, y - TAG_GAP - size.1),
+                    (x + size.0 / 2.0, y - TAG_GAP),
+                )
+            } else {
+                (
+                    (x + TAG_GAP, y - size.1 / 2.0),
+                    (x + TAG_GAP + size.0, y + size.1 / 2.0),
+                )
+            };
+            if !obstacles.iter().any(|o| overlap(o, &bounds)) {
+                return Some(bounds);
+            }
+            first.get_or_insert(bounds);
+        }
+    }
+    first
+}
+
+/// estimate the outline of the text from the number of characters.
+fn text_bounds(text: &str, at: (f64, f64), angle: f64, effects: &Effects) -> Bounds {
+    let lines = text.lines().count().max(1) as f64;
+    let width = text
+        .lines()
+        .map(|line| text_width(line, effects.font_size.0))
+        .fold(0.0, f64::max);
+    let height = lines * effects.font_size.1 * 1.5;
+    let justify = |name: &str| effects.justify.iter().any(|j| j == name);
+    let x = if justify("left") {
+        (0.0, width)
+    } else if justify("right") {
+        (-width, 0.0)
+    } else {
+        (-width / 2.0, width / 2.0)
+    };
+    let y = if justify("bottom") {
+        (-height, 0.0)
+    } else if justify("top") {
+        (0.0, height)
+    } else {
+        (-height / 2.0, height / 2.0)
+    };
+    // the text is horizontal or vertical, the y axis points down.
+    let (x, y) = match ((angle / 90.0).round() as i32).rem_euclid(4) {
+        1 => (y, (-x.1, -x.0)),
+        2 => ((-x.1, -x.0), (-y.1, -y.0)),
+        3 => ((-y.1, -y.0), x),
+        _ => (x, y),
+    };
+    ((at.0 + x.0, at.1 + y.0), (at.0 + x.1, at.1 + y.1))
+}
+
+fn overlap(a: &Bounds, b: &Bounds) -> bool {
+    a.0 .0 < b.1 .0 && b.0 .0 < a.1 .0 && a.0 .1 < b.1 .1 && b.0 .1 < a.1 .1
+}
+
+pub(crate) fn same(a: (f64, f64), b: (f64, f64)) -> bool {
+    (a.0 - b.0).abs() < TOLERANCE && (a.1 - b.1).abs() < TOLERANCE
+}
+
+/// test if the point is on the wire between the end points.
+pub(crate) fn on_wire(start: (f64, f64), end: (f64, f64), pt: (f64, f64)) -> bool {
+    let cross = (end.0 - start.0) * (pt.1 - start.1) - (end.1 - start.1) * (pt.0 - start.0);
+    cross.abs() < TOLERANCE
+        && pt.0 >= start.0.min(end.0) - TOLERANCE
+        && pt.0 <= start.0.max(end.0) + TOLERANCE
+        && pt.1 >= start.1.min(end.1) - TOLERANCE
+        && pt.1 <= start.1.max(end.1) + TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{place_tag, text_bounds, Connections};
+    use crate::extra::Extra;
+    use elektron_sexp::{Effects, Schema};
+
+    #[test]
+    fn junctions_and_dangling() {
+        let connections = Connections {
+            wires: vec![
+                ((0.0, 0.0), (20.0, 0.0)),
+                ((10.0, 0.0), (10.0, 10.0)),
+                ((20.0, 0.0), (20.0, 10.0)),
+                ((30.0, 0.0), (40.0, 0.0)),
+            ],
+            pins: vec![(0.0, 0.0), (20.0, 10.0), (10.0, 10.0), (50.0, 50.0)],
+            symbols: Vec::new(),
+            junctions: Vec::new(),
+            terminals: vec![(40.0, 0.0)],
+            texts: Vec::new(),
+        };
+        assert_eq!(vec![(10.0, 0.0)], connections.missing_junctions());
+        assert_eq!(vec![(30.0, 0.0)], connections.dangling_wires());
+        assert_eq!(vec![(50.0, 50.0)], connections.dangling_pins());
+        let segments = connections.segments();
+        assert_eq!(2, segments.len());
+        assert_eq!(3, segments[0].len());
+        assert_eq!(vec![((30.0, 0.0), (40.0, 0.0))], segments[1]);
+        assert_eq!(segments[1], connections.touching(&[(40.0, 0.0)]));
+        assert!(connections.touching(&[(50.0, 50.0)]).is_empty());
+    }
+    #[test]
+    fn hidden_pins() {
+        let path = "/tmp/hidden-pins.kicad_sch";
+        std::fs::write(
+            path,
+            r#"(kicad_sch (version 20211123) (generator eeschema)
+  (uuid 7f1a2b3c-4d5e-4f60-8a71-92b3c4d5e6f7)
+  (paper "A4")
+  (lib_symbols
+    (symbol "test:U" (in_bom yes) (on_board yes)
+      (property "Reference" "U" (id 0) (at 0 0 0) (effects (font (size 1.27 1.27))))
+      (symbol "U_1_1"
+        (pin input line (at -5.08 0 0) (length 2.54)
+          (name "IN" (effects (font (size 1.27 1.27))))
+          (number "1" (effects (font (size 1.27 1.27))))
+        )
+        (pin power_in line (at 0 5.08 270) (length 2.54) hide
+          (name "V+" (effects (font (size 1.27 1.27))))
+          (number "2" (effects (font (size 1.27 1.27))))
+        )
+      )
+    )
+  )
+  (symbol (lib_id "test:U") (at 50.8 50.8 0) (unit 1)
+    (in_bom yes) (on_board yes)
+    (uuid 8a2b3c4d-5e6f-4071-8b92-a3c4d5e6f708)
+    (property "Reference" "U1" (id 0) (at 50.8 45.72 0))
+  )
+  (sheet_instances
+    (path "/" (page "1"))
+  )
+)"#,
+        )
+        .unwrap();
+        let schema = Schema::load(path).unwrap();
+        let connections = Connections::new(&schema, 0, &HashMap::new(), &Extra::default()).unwrap();
+        assert_eq!(2, connections.pins.len());
+        assert_eq!(vec![(45.72, 50.8)], connections.dangling_pins());
+    }
+    #[test]
+    fn text_outline() {
+        let mut effects = Effects::new();
+        effects.font_size = (2.0, 2.0);
+        effects.justify = vec![String::from("left"), String::from("bottom")];
+        assert_eq!(
+            ((10.0, 7.0), (13.6, 10.0)),
+            text_bounds("ABC", (10.0, 10.0), 0.0, &effects)
+        );
+        // the vertical text reads from the bottom to the top.
+        assert_eq!(
+            ((7.0, 6.4), (10.0, 10.0)),
+            text_bounds("ABC", (10.0, 10.0), 90.0, &effects)
+        );
+    }
+    #[test]
+    fn tag_placement() {
+        let segment = vec![((0.0, 10.0), (20.0, 10.0)), ((0.0, 0.0), (0.0, 10.0))];
+        // the middle of the long wire.
+        assert_eq!(
+            Some(((8.0, 8.7), (12.0, 9.7))),
+            place_tag(&segment, (4.0, 1.0), &[])
+        );
+        // the middle is blocked, the tag moves along the wire.
+        let tag = place_tag(&segment, (4.0, 1.0), &[((7.0, 5.0), (13.0, 15.0))]).unwrap();
+        assert_eq!(((3.0, 8.7), (7.0, 9.7)), tag);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::error::Error;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref SHEET: Regex = Regex::new(r"^\*+\s*Sheet\s+(.*)$").unwrap();
+    static ref VIOLATION: Regex = Regex::new(r"^\[([^\]]+)\]:\s*(.*)$").unwrap();
+    static ref SEVERITY: Regex = Regex::new(r"^;\s*(?:Severity:\s*)?(\w+)(.*)$").unwrap();
+    static ref POSITION: Regex =
+        Regex::new(r"^@\(\s*(-?[\d.]+)\s*(mm|in|mils)?\s*,\s*(-?[\d.]+)\s*(mm|in|mils)?\s*\)")
+            .unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Exclusion,
+}
+
+impl Severity {
+    fn from(name: &str) -> Self {
+        match name {
+            "error" => Severity::Error,
+            "exclusion" | "excluded" => Severity::Exclusion,
+            _ => Severity::Warning,
+        }
+    }
+    /// get the marker color like the KiCad defaults.
+    pub fn color(&self) -> (f64, f64, f64, f64) {
+        match self {
+            Severity::Error => (1.0, 0.0, 0.0, 0.8),
+            Severity::Warning => (0.0, 1.0, 0.0, 0.8),
+            Severity::Exclusion => (0.6, 0.6, 0.6, 0.8),
+        }
+    }
+}
+
+/// An ERC marker, the position is in sheet mm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub pos: (f64, f64),
+    pub severity: Severity,
+    pub message: String,
+    /// the sheet path, `/` is the root sheet.
+    pub sheet: String,
+}
+
+impl Marker {
+    pub fn new(pos: (f64, f64), severity: Severity, message: &str, sheet: &str) -> Self {
+        Self {
+            pos,
+            severity,
+            message: message.to_string(),
+            sheet: sheet.to_string(),
+        }
+    }
+    /// load the markers from a KiCad ERC report, the report is a JSON or a text file.
+    pub fn load(filename: &str) -> Result<Vec<Self>, Error> {
+        let content = std::fs::read_to_string(filename)?;
+        if content.trim_start().starts_with('{') {
+            Self::from_json(&content)
+        } else {
+            Ok(Self::from_report(&content))
+        }
+    }
+    /// get the markers from the JSON ERC report, the marker is placed at the first item.
+    pub fn from_json(content: &str) -> Result<Vec<Self>, Error> {
+        let report = json::parse(content).map_err(|_| Error::ParseError)?;
+        let scale = match report["coordinate_units"].as_str() {
+            Some("in") => 25.4,
+            Some("mils") => 0.0254,
+            _ => 1.0,
+        };
+        let mut markers = Vec::new();
+        for sheet in report["sheets"].members() {
+            let path = sheet["path"].as_str().unwrap_or("/");
+            for violation in sheet["violations"].members() {
+                let item = &violation["items"][0];
+                // the excluded violations keep the severity of the rule.
+                let severity = if violation["excluded"].as_bool().unwrap_or(false) {
+                    Severity::Exclusion
+                } else {
+                    Severity::from(violation["severity"].as_str().unwrap_or("warning"))
+                };
+                markers.push(Marker::new(
+                    (
+                        item["pos"]["x"].as_f64().unwrap_or(0.0) * scale,
+                        item["pos"]["y"].as_f64().unwrap_or(0.0) * scale,
+                    ),
+                    severity,
+                    violation["description"].as_str().unwrap_or_default(),
+                    path,
+                ));
+            }
+        }
+        Ok(markers)
+    }
+    /// get the markers from the text ERC report.
+    pub fn from_report(content: &str) -> Vec<Self> {
+        let mut markers: Vec<Marker> = Vec::new();
+        let mut sheet = String::from("/");
+        // the marker of the current violation, it is added with the first position.
+        let mut current: Option<Marker> = None;
+        for line in content.lines().map(|l| l.trim()) {
+            if let Some(caps) = SHEET.captures(line) {
+                sheet = caps[1].trim().to_string();
+            } else if let Some(caps) = VIOLATION.captures(line) {
+                current = Some(Marker::new((0.0, 0.0), Severity::Warning, &caps[2], &sheet));
+            } else if let Some(caps) = SEVERITY.captures(line) {
+                if let Some(marker) = &mut current {
+                    marker.severity = if caps[2].contains("excluded") {
+                        Severity::Exclusion
+                    } else {
+                        Severity::from(&caps[1])
+                    };
+                }
+            } else if let Some(caps) = POSITION.captures(line) {
+                if let Some(mut marker) = current.take() {
+                    let unit = |value: &str, unit: Option<regex::Match>| {
+                        let value: f64 = value.parse().unwrap_or(0.0);
+                        match unit.map(|u| u.as_str()) {
+                            Some("in") => value * 25.4,
+                            Some("mils") => value * 0.0254,
+                            _ => value,
+                        }
+                    };
+                    marker.pos = (unit(&caps[1], caps.get(2)), unit(&caps[3], caps.get(4)));
+                    markers.push(marker);
+                }
+            }
+        }
+        markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Marker, Severity};
+
+    #[test]
+    fn json_report() {
+        let markers = Marker::from_json(
+            r#"{
+                "coordinate_units": "mm",
+                "sheets": [{
+                    "path": "/",
+                    "violations": [{
+                        "description": "Pin not connected",
+                        "severity": "error",
+                        "type": "pin_not_connected",
+                        "items": [{"description": "Symbol U1 Pin 3", "pos": {"x": 101.6, "y": 63.5}}]
+                    }, {
+                        "description": "Pin not connected",
+                        "severity": "error",
+                        "excluded": true,
+                        "type": "pin_not_connected",
+                        "items": [{"description": "Symbol U2 Pin 3", "pos": {"x": 50.8, "y": 63.5}}]
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                Marker::new((101.6, 63.5), Severity::Error, "Pin not connected", "/"),
+                Marker::new((50.8, 63.5), Severity::Exclusion, "Pin not connected", "/")
+            ],
+            markers
+        );
+    }
+    #[test]
+    fn text_report() {
+        let markers = Marker::from_report(
+            r#"ERC report (Tue 10 Jan 2023, Encoding UTF8)
+
+***** Sheet /
+[pin_not_connected]: Pin not connected
+    ; error
+    @(101.60 mm, 63.50 mm): Symbol U1 Pin 3 [Output, Line]
+
+***** Sheet /filter/
+[lib_symbol_issues]: Symbol not found in library
+    ; warning
+    @(1.0000 in, 2.0000 in): Symbol R1
+[pin_not_connected]: Pin not connected
+    ; Severity: error (excluded)
+    @(50.80 mm, 63.50 mm): Symbol U2 Pin 3 [Output, Line]
+"#,
+        );
+        assert_eq!(3, markers.len());
+        assert_eq!((101.6, 63.5), markers[0].pos);
+        assert_eq!(Severity::Error, markers[0].severity);
+        assert_eq!("/filter/", markers[1].sheet);
+        assert_eq!((25.4, 50.8), markers[1].pos);
+        assert_eq!(Severity::Warning, markers[1].severity);
+        assert_eq!(Severity::Exclusion, markers[2].severity);
+    }
+}
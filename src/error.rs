@@ -24,6 +24,14 @@ pub enum Error {
     LinraryNotFound(String),
     #[error("Unknown image type \"{0}\", must be jpg, png or pdf.")]
     UnknownImageType(String),
+    #[error("Invalid pattern \"{0}\": {1}")]
+    Pattern(String, String),
+    #[error("Simulation vector \"{0}\" not found")]
+    VectorNotFound(String),
+    #[error("Invalid range {1} to {2} of the axis \"{0}\"")]
+    AxisRange(String, f64, f64),
+    #[error("Animation is only written as png, not {0}")]
+    AnimationImageType(String),
 }
 
 impl std::convert::From<std::io::Error> for Error {
@@ -46,8 +54,67 @@ impl std::convert::From<cairo::IoError> for Error {
         Error::IoError(err.to_string())
     }
 }
+impl std::convert::From<cairo::StreamWithError> for Error {
+    fn from(err: cairo::StreamWithError) -> Self {
+        Error::IoError(err.to_string())
+    }
+}
+impl std::convert::From<png::EncodingError> for Error {
+    fn from(err: png::EncodingError) -> Self {
+        Error::IoError(err.to_string())
+    }
+}
+impl std::convert::From<png::DecodingError> for Error {
+    fn from(err: png::DecodingError) -> Self {
+        Error::IoError(err.to_string())
+    }
+}
 impl std::convert::From<elektron_sexp::Error> for Error {
     fn from(err: elektron_sexp::Error) -> Self {
         Error::IoError(err.to_string())
     }
 }
+
+/// Non fatal problems found while plotting.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum Warning {
+    #[error("Library symbol \"{0}\" not found, placeholder drawn.")]
+    MissingLibrarySymbol(String),
+    #[error("Unsupported element: {0}")]
+    UnsupportedElement(String),
+    #[error("Can not find Theme item: {0} {1}, default used.")]
+    MissingThemeKey(String, String),
+    #[error("Library \"{0}\" not found in the search paths.")]
+    LibraryNotFound(String),
+    #[error("Font \"{0}\" not available, fallback font used.")]
+    FontFallback(String),
+    #[error("Netlist required for the {0}, the option is ignored.")]
+    MissingNetlist(&'static str),
+    #[error("Unknown paper \"{0}\", default used.")]
+    UnknownPaper(String),
+    #[error("Schema file unknown, the elements of the file like images and graphics are skipped.")]
+    MissingSchemaFile,
+    #[error("Can not load the file {path}: {error}")]
+    FileLoad { path: String, error: String },
+    #[error("Can not load the library {path}: {error}")]
+    LibraryLoad { path: String, error: String },
+    #[error("Unknown theme item: {0} ({1})")]
+    UnknownThemeItem(String, String),
+    #[error("View \"{0}\" not found on page {1}.")]
+    ViewNotFound(String, usize),
+    #[error("Can not plot the {element}: {error}")]
+    PlotError { element: String, error: String },
+}
+
+impl Warning {
+    /// report the error of a plot element, missing theme keys are reported as such.
+    pub(crate) fn from_error(element: &str, err: Error) -> Self {
+        match err {
+            Error::Theme(kind, name) => Warning::MissingThemeKey(kind, name),
+            err => Warning::PlotError {
+                element: element.to_string(),
+                error: err.to_string(),
+            },
+        }
+    }
+}
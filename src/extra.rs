@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::border::SheetInfo;
+use crate::cairo_plotter::{Image, PlotItem, BITMAP_PPI};
+use crate::error::{Error, Warning};
+use crate::sexp::Node;
+use base64::{engine::general_purpose, Engine as _};
+use elektron_sexp::{Effects, Stroke};
+use ndarray::arr1;
+
+/// A bitmap embedded in the schema, the position is the center of the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaImage {
+    pub at: (f64, f64),
+    pub scale: f64,
+    pub data: Vec<u8>,
+}
+
+/// The fill of a graphic item, the type `color` uses the custom fill color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub fill_type: String,
+    pub color: Option<(f64, f64, f64, f64)>,
+}
+
+/// The free standing graphic items of KiCad 7 and later.
+#[derive(Debug, Clone)]
+pub enum Graphic {
+    Rectangle {
+        start: (f64, f64),
+        end: (f64, f64),
+        stroke: Stroke,
+        fill: Fill,
+    },
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+        stroke: Stroke,
+        fill: Fill,
+    },
+    Arc {
+        start: (f64, f64),
+        mid: (f64, f64),
+        end: (f64, f64),
+        stroke: Stroke,
+        fill: Fill,
+    },
+    Bezier {
+        pts: Vec<(f64, f64)>,
+        stroke: Stroke,
+        fill: Fill,
+    },
+    TextBox {
+        text: String,
+        at: (f64, f64),
+        angle: f64,
+        size: (f64, f64),
+        /// left, top, right and bottom margin, older files have no margins.
+        margins: Option<(f64, f64, f64, f64)>,
+        stroke: Stroke,
+        fill: Fill,
+        effects: Effects,
+    },
+}
+
+/// A field of a directive label, like the `Netclass` of a netclass flag.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub key: String,
+    pub value: String,
+    pub at: (f64, f64),
+    pub angle: f64,
+    pub effects: Effects,
+}
+
+/// A directive label, the shape is `dot`, `round`, `diamond` or `rectangle`.
+#[derive(Debug, Clone)]
+pub struct NetclassFlag {
+    pub at: (f64, f64),
+    pub angle: f64,
+    pub length: f64,
+    pub shape: String,
+    pub fields: Vec<Field>,
+}
+
+impl NetclassFlag {
+    /// get the netclass name of the flag.
+    pub fn netclass(&self) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.key == "Netclass")
+            .map(|f| f.value.as_str())
+    }
+}
+
+/// Elements of a schema file which are not part of the elektron_sexp model.
+///
+/// The elements are read from the raw schema file of the page.
+#[derive(Debug, Clone, Default)]
+pub struct Extra {
+    /// the paper like `A4 portrait` or `User 200 150`.
+    pub paper: Option<String>,
+    /// the generator and version of the file like `eeschema 20230121`.
+    pub version: Option<String>,
+    pub images: Vec<SchemaImage>,
+    pub graphics: Vec<Graphic>,
+    pub netclass_flags: Vec<NetclassFlag>,
+    /// the members of the bus aliases.
+    pub bus_aliases: HashMap<String, Vec<String>>,
+    /// the positions of the hierarchical sheet pins.
+    pub sheet_pins: Vec<(f64, f64)>,
+    /// the uuids of the symbols marked as do not populate.
+    pub dnp: Vec<String>,
+    /// the elements that could not be read.
+    pub warnings: Vec<Warning>,
+}
+
+impl Extra {
+    pub fn load(filename: &Path) -> Result<Self, Error> {
+        Self::from(std::fs::read_to_string(filename)?)
+    }
+    pub fn from(content: String) -> Result<Self, Error> {
+        let root = Node::parse(content).ok_or(Error::ParseError)?;
+        if root.name != "kicad_sch" {
+            return Err(Error::ParseError);
+        }
+        let mut extra = Extra {
+            paper: root.get("paper").map(|paper| paper.values.join(" ")),
+            version: version(&root),
+            ..Extra::default()
+        };
+        for node in root.all("image") {
+            let at = node.get("at");
+            // the base64 data is split into multiple strings.
+            let data: String = node
+                .get("data")
+                .map(|d| d.values.concat())
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+            let at = (
+                at.and_then(|a| a.f64(0)).unwrap_or(0.0),
+                at.and_then(|a| a.f64(1)).unwrap_or(0.0),
+            );
+            match general_purpose::STANDARD.decode(data) {
+                Ok(data) => extra.images.push(SchemaImage {
+                    at,
+                    scale: node.child_f64("scale").unwrap_or(1.0),
+                    data,
+                }),
+                Err(err) => extra.warnings.push(Warning::UnsupportedElement(format!(
+                    "image at {} {}: {}",
+                    at.0, at.1, err
+                ))),
+            }
+        }
+        for node in &root.nodes {
+            match node.name.as_str() {
+                "rectangle" => extra.graphics.push(Graphic::Rectangle {
+                    start: xy(node.get("start")),
+                    end: xy(node.get("end")),
+                    stroke: stroke(node.get("stroke")),
+                    fill: fill(node.get("fill")),
+                }),
+                "circle" => extra.graphics.push(Graphic::Circle {
+                    center: xy(node.get("center")),
+                    radius: node.child_f64("radius").unwrap_or(0.0),
+                    stroke: stroke(node.get("stroke")),
+                    fill: fill(node.get("fill")),
+                }),
+                "arc" => extra.graphics.push(Graphic::Arc {
+                    start: xy(node.get("start")),
+                    mid: xy(node.get("mid")),
+                    end: xy(node.get("end")),
+                    stroke: stroke(node.get("stroke")),
+                    fill: fill(node.get("fill")),
+                }),
+                "bezier" => extra.graphics.push(Graphic::Bezier {
+                    pts: node
+                        .get("pts")
+                        .map(|pts| pts.all("xy").map(|pt| xy(Some(pt))).collect())
+                        .unwrap_or_default(),
+                    stroke: stroke(node.get("stroke")),
+                    fill: fill(node.get("fill")),
+                }),
+                "text_box" => {
+                    let at = node.get("at");
+                    let margins = node.get("margins").map(|m| {
+                        (
+                            m.f64(0).unwrap_or(0.0),
+                            m.f64(1).unwrap_or(0.0),
+                            m.f64(2).unwrap_or(0.0),
+                            m.f64(3).unwrap_or(0.0),
+                        )
+                    });
+                    extra.graphics.push(Graphic::TextBox {
+                        text: node.value(0).unwrap_or_default().to_string(),
+                        at: xy(at),
+                        angle: at.and_then(|a| a.f64(2)).unwrap_or(0.0),
+                        size: xy(node.get("size")),
+                        margins,
+                        stroke: stroke(node.get("stroke")),
+                        fill: fill(node.get("fill")),
+                        // text boxes are aligned to the left top corner by default.
+                        effects: effects(node.get("effects"), &["left", "top"]),
+                    })
+                }
+                "netclass_flag" => {
+                    let at = node.get("at");
+                    extra.netclass_flags.push(NetclassFlag {
+                        at: xy(at),
+                        angle: at.and_then(|a| a.f64(2)).unwrap_or(0.0),
+                        length: node.child_f64("length").unwrap_or(2.54),
+                        shape: node
+                            .get("shape")
+                            .and_then(|s| s.value(0))
+                            .unwrap_or("round")
+                            .to_string(),
+                        fields: node
+                            .all("property")
+                            .map(|property| {
+                                let at = property.get("at");
+                                Field {
+                                    key: property.value(0).unwrap_or_default().to_string(),
+                                    value: property.value(1).unwrap_or_default().to_string(),
+                                    at: xy(at),
+                                    angle: at.and_then(|a| a.f64(2)).unwrap_or(0.0),
+                                    effects: effects(property.get("effects"), &[]),
+                                }
+                            })
+                            .collect(),
+                    })
+                }
+                "symbol" if node.flag("dnp") => {
+                    if let Some(uuid) = node.get("uuid").and_then(|u| u.value(0)) {
+                        extra.dnp.push(uuid.to_string());
+                    }
+                }
+                "sheet" => {
+                    for pin in node.all("pin") {
+                        extra.sheet_pins.push(xy(pin.get("at")));
+                    }
+                }
+                "bus_alias" => {
+                    if let Some(name) = node.value(0) {
+                        extra.bus_aliases.insert(
+                            name.to_string(),
+                            node.get("members")
+                                .map(|m| m.values.clone())
+                                .unwrap_or_default(),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(extra)
+    }
+    /// plot the images of the schema.
+    pub fn images(&self) -> Vec<PlotItem> {
+        self.images
+            .iter()
+            .map(|image| {
+                PlotItem::Image(
+                    0,
+                    Image::new(
+                        arr1(&[image.at.0, image.at.1]),
+                        25.4 / BITMAP_PPI * image.scale,
+                        image.data.clone(),
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+/// get the coordinates of a node like `(at 10 20)`.
+/// get the generator and version, newer files have a generator version.
+fn version(root: &Node) -> Option<String> {
+    let version = root
+        .get("generator_version")
+        .or_else(|| root.get("version"))
+        .and_then(|v| v.value(0));
+    let parts: Vec<&str> = [root.get("generator").and_then(|g| g.value(0)), version]
+        .into_iter()
+        .flatten()
+        .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+fn xy(node: Option<&Node>) -> (f64, f64) {
+    (
+        node.and_then(|n| n.f64(0)).unwrap_or(0.0),
+        node.and_then(|n| n.f64(1)).unwrap_or(0.0),
+    )
+}
+
+/// get the color of a node like `(color 255 0 0 1)`, a color with zero alpha is not set.
+fn color(node: Option<&Node>) -> Option<(f64, f64, f64, f64)> {
+    let node = node?;
+    let color = (
+        node.f64(0)? / 255.0,
+        node.f64(1)? / 255.0,
+        node.f64(2)? / 255.0,
+        node.f64(3)?,
+    );
+    if color.3 == 0.0 {
+        None
+    } else {
+        Some(color)
+    }
+}
+
+/// get the stroke, unset values are zero and taken from the theme.
+fn stroke(node: Option<&Node>) -> Stroke {
+    Stroke {
+        width: node.and_then(|n| n.child_f64("width")).unwrap_or(0.0),
+        linetype: node
+            .and_then(|n| n.get("type"))
+            .and_then(|t| t.value(0))
+            .unwrap_or("default")
+            .to_string(),
+        color: color(node.and_then(|n| n.get("color"))).unwrap_or((0.0, 0.0, 0.0, 0.0)),
+    }
+}
+
+fn fill(node: Option<&Node>) -> Fill {
+    Fill {
+        fill_type: node
+            .and_then(|n| n.get("type"))
+            .and_then(|t| t.value(0))
+            .unwrap_or("none")
+            .to_string(),
+        color: color(node.and_then(|n| n.get("color"))),
+    }
+}
+
+/// get the effects, unset values are zero and taken from the theme.
+fn effects(node: Option<&Node>, justify: &[&str]) -> Effects {
+    let font = node.and_then(|n| n.get("font"));
+    let size = font.and_then(|f| f.get("size"));
+    Effects {
+        font: font
+            .and_then(|f| f.get("face"))
+            .and_then(|f| f.value(0))
+            .unwrap_or_default()
+            .to_string(),
+        color: color(font.and_then(|f| f.get("color"))).unwrap_or((0.0, 0.0, 0.0, 0.0)),
+        font_size: (
+            size.and_then(|s| s.f64(0)).unwrap_or(0.0),
+            size.and_then(|s| s.f64(1)).unwrap_or(0.0),
+        ),
+        thickness: font.and_then(|f| f.child_f64("thickness")).unwrap_or(0.0),
+        bold: font.map(|f| f.flag("bold")).unwrap_or(false),
+        italic: font.map(|f| f.flag("italic")).unwrap_or(false),
+        line_spacing: font
+            .and_then(|f| f.child_f64("line_spacing"))
+            .unwrap_or(0.0),
+        justify: node
+            .and_then(|n| n.get("justify"))
+            .map(|j| j.values.clone())
+            .unwrap_or_else(|| justify.iter().map(|j| j.to_string()).collect()),
+        hide: node.map(|n| n.flag("hide")).unwrap_or(false),
+    }
+}
+
+/// get the file of the page, the sheet files are relative to the root schema.
+pub fn page_file(root: &str, sheet: &SheetInfo, page: usize) -> PathBuf {
+    if page == 0 {
+        PathBuf::from(root)
+    } else {
+        Path::new(root)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&sheet.filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Extra, Graphic};
+
+    #[test]
+    fn schema_image() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20211123) (generator eeschema)
+                (image (at 100 50) (scale 0.5)
+                    (uuid 0fc2b7c0-6b1d-4a2b-9f0e-1c5d1f6a3a10)
+                    (data
+                        iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR4nGNgYGBgAAAABQABpfZFQAAA
+                        AABJRU5ErkJggg==
+                    )
+                )
+            )"#,
+        ))
+        .unwrap();
+        assert_eq!(1, extra.images.len());
+        assert_eq!((100.0, 50.0), extra.images[0].at);
+        assert_eq!(0.5, extra.images[0].scale);
+        assert_eq!(b"\x89PNG", &extra.images[0].data[0..4]);
+    }
+    #[test]
+    fn broken_image() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20211123) (generator eeschema)
+                (image (at 10 20) (data "not base64!"))
+                (image (at 100 50) (data "iVBORw0KGgo="))
+                (rectangle (start 10 10) (end 50 30) (stroke (width 0) (type default)) (fill (type none)))
+            )"#,
+        ))
+        .unwrap();
+        assert_eq!(1, extra.images.len());
+        assert_eq!((100.0, 50.0), extra.images[0].at);
+        assert_eq!(1, extra.graphics.len());
+        assert_eq!(1, extra.warnings.len());
+    }
+    #[test]
+    fn schema_graphics() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20230121) (generator eeschema)
+                (rectangle (start 10 10) (end 50 30)
+                    (stroke (width 0.5) (type dash) (color 255 0 0 1))
+                    (fill (type color) (color 0 0 255 0.5))
+                )
+                (circle (center 20 20) (radius 5) (stroke (width 0) (type default)) (fill (type none)))
+                (arc (start 0 10) (mid 10 0) (end 20 10) (stroke (width 0) (type default)) (fill (type none)))
+                (bezier (pts (xy 0 0) (xy 10 0) (xy 10 10) (xy 20 10)) (stroke (width 0) (type default)) (fill (type none)))
+                (text_box "Note" (at 60 10 0) (size 30 10) (margins 1 1 1 1)
+                    (stroke (width 0) (type default)) (fill (type background))
+                    (effects (font (size 1.27 1.27)) (justify right bottom))
+                )
+            )"#,
+        ))
+        .unwrap();
+        assert_eq!(5, extra.graphics.len());
+        if let Graphic::Rectangle { stroke, fill, .. } = &extra.graphics[0] {
+            assert_eq!((1.0, 0.0, 0.0, 1.0), stroke.color);
+            assert_eq!("dash", stroke.linetype);
+            assert_eq!(Some((0.0, 0.0, 1.0, 0.5)), fill.color);
+        } else {
+            panic!("rectangle expected");
+        }
+        if let Graphic::Bezier { pts, .. } = &extra.graphics[3] {
+            assert_eq!(4, pts.len());
+        } else {
+            panic!("bezier expected");
+        }
+        if let Graphic::TextBox {
+            text,
+            size,
+            effects,
+            ..
+        } = &extra.graphics[4]
+        {
+            assert_eq!("Note", text);
+            assert_eq!((30.0, 10.0), *size);
+            assert_eq!(
+                vec![String::from("right"), String::from("bottom")],
+                effects.justify
+            );
+        } else {
+            panic!("text box expected");
+        }
+    }
+    #[test]
+    fn netclass_flag() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20230121) (generator eeschema)
+                (netclass_flag "" (length 5.08) (shape diamond) (at 100 50 90)
+                    (effects (font (size 1.27 1.27)) (justify left bottom))
+                    (property "Netclass" "Power" (at 95 46 90)
+                        (effects (font (size 1.27 1.27) italic) (justify left))
+                    )
+                )
+            )"#,
+        ))
+        .unwrap();
+        let flag = &extra.netclass_flags[0];
+        assert_eq!((100.0, 50.0), flag.at);
+        assert_eq!(90.0, flag.angle);
+        assert_eq!(5.08, flag.length);
+        assert_eq!("diamond", flag.shape);
+        assert_eq!(Some("Power"), flag.netclass());
+        assert!(flag.fields[0].effects.italic);
+    }
+    #[test]
+    fn paper() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20211123) (generator eeschema) (paper "A3" portrait))"#,
+        ))
+        .unwrap();
+        assert_eq!(Some(String::from("A3 portrait")), extra.paper);
+        assert_eq!(Some(String::from("eeschema 20211123")), extra.version);
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20211123) (generator eeschema) (paper "User" 200 150))"#,
+        ))
+        .unwrap();
+        assert_eq!(Some(String::from("User 200 150")), extra.paper);
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20231120) (generator "eeschema") (generator_version "8.0"))"#,
+        ))
+        .unwrap();
+        assert_eq!(Some(String::from("eeschema 8.0")), extra.version);
+    }
+    #[test]
+    fn dnp() {
+        let extra = Extra::load(std::path::Path::new("files/dnp.kicad_sch")).unwrap();
+        assert_eq!(
+            vec![String::from("a1b2c3d4-2222-4a5b-8c6d-7e8f9a0b2222")],
+            extra.dnp
+        );
+    }
+    #[test]
+    fn bus_alias() {
+        let extra = Extra::from(String::from(
+            r#"(kicad_sch (version 20211123) (generator eeschema)
+                (bus_alias "I2C" (members "SDA" "SCL"))
+            )"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            Some(&vec![String::from("SDA"), String::from("SCL")]),
+            extra.bus_aliases.get("I2C")
+        );
+    }
+}
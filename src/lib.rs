@@ -1,21 +1,54 @@
+mod bode;
 mod border;
+mod bus;
 mod cairo_plotter;
+mod chart;
+mod circuit;
+mod connections;
+mod erc;
 mod error;
+mod extra;
+mod library;
+mod nets;
 mod pcb;
 mod schema;
+mod sexp;
+mod simulation;
+mod style;
+mod subcircuit;
 mod theme;
+mod variables;
+mod view;
+mod worksheet;
 
 use lazy_static::lazy_static;
-use rand::Rng;
-use std::env::temp_dir;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::rc::Rc;
 use std::sync::Mutex;
 
-pub use self::cairo_plotter::{CairoPlotter, ImageType, PlotItem, Plotter};
+use self::border::{cursor_bar, draw_cursor};
+pub use self::cairo_plotter::{paper, CairoPlotter, ImageType, PlotItem, Plotter};
+use self::extra::Extra;
 pub use self::theme::{Theme, Themer};
+pub use bode::Bode;
+pub use chart::{Analysis, Axis, Chart, Trace};
+pub use circuit::{Component, Kind};
 use elektron_spice::{Circuit, Netlist};
-pub use error::Error;
+pub use erc::{Marker, Severity};
+pub use error::{Error, Warning};
+pub use library::SymbolLibrary;
+pub use nets::Highlight;
+use nets::{colors_mut, net_color};
+pub use simulation::{format_si, OperatingPoint};
+use simulation::{sample, voltage_color};
+pub use style::{Style, StyleRule};
+pub use subcircuit::Subcircuit;
+pub use variables::Variables;
+pub use view::View;
+pub use worksheet::Worksheet;
 
 lazy_static! {
     static ref PLOT: Mutex<Vec<Vec<u8>>> = Mutex::new(vec![]);
@@ -62,13 +95,342 @@ fn check_directory(filename: &str) -> Result<(), Error> {
     let path = std::path::Path::new(filename);
     let parent = path.parent();
     if let Some(parent) = parent {
-        if parent.to_str().unwrap() != "" && !parent.exists() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
             std::fs::create_dir_all(parent)?;
         }
     }
     Ok(())
 }
 
+/// Cairo takes ownership of the output stream, the buffer is shared to get the content back.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn image_type(image_type: &str) -> Result<ImageType, Error> {
+    if image_type == "pdf" {
+        Ok(ImageType::Pdf)
+    } else if image_type == "png" {
+        Ok(ImageType::Png)
+    } else if image_type == "svg" {
+        Ok(ImageType::Svg)
+    } else {
+        Err(Error::UnknownImageType(image_type.to_string()))
+    }
+}
+
+fn theme(theme: &str) -> Theme {
+    if theme == "mono" {
+        Theme::mono()
+    } else {
+        Theme::kicad_2000()
+    }
+}
+
+/// Options for plotting a schema.
+#[derive(Clone)]
+pub struct PlotOptions {
+    pub scale: f64,
+    pub border: bool,
+    pub theme: String,
+    pub image_type: ImageType,
+    /// paper like `A3 portrait`, overrides the paper of the schema.
+    pub paper: Option<String>,
+    /// search paths for symbols missing in the schema.
+    pub library: SymbolLibrary,
+    /// KiCad drawing sheet used instead of the default border.
+    pub worksheet: Option<Worksheet>,
+    /// file name of the schema, shown in the title block.
+    pub filename: Option<String>,
+    /// path of the schema file, the images, graphics, netclass flags, bus aliases
+    /// and sheet pins are read from the files of the pages.
+    ///
+    /// The `filename` is used when it is an existing file, otherwise these elements
+    /// are skipped with a warning.
+    pub schema_path: Option<String>,
+    /// project text variables, like `${VERSION}`.
+    pub variables: HashMap<String, String>,
+    /// PNG data of a logo placed beside the title block.
+    pub logo: Option<Vec<u8>>,
+    /// wire colors by netclass name, the netclass is assigned with netclass flags.
+    pub netclass_colors: HashMap<String, (f64, f64, f64, f64)>,
+    /// add the junctions missing at wire T-connections and pins on wires.
+    pub junctions: bool,
+    /// mark the dangling wire ends and unconnected pins.
+    pub dangling: bool,
+    /// ERC markers drawn with a legend on their sheets.
+    pub markers: Vec<Marker>,
+    /// color the wires, junctions, labels and pins by net, requires a netlist.
+    pub net_colors: bool,
+    /// user colors of the nets, the other nets get a color from the palette.
+    pub net_color_map: HashMap<String, (f64, f64, f64, f64)>,
+    /// draw a legend with the net colors.
+    pub net_legend: bool,
+    /// highlight nets and dim the other items, requires a netlist.
+    pub highlight: Option<Highlight>,
+    /// draw one node name per net segment, requires a netlist.
+    ///
+    /// The names are drawn on the wires, nets without wires get no name.
+    pub node_names: bool,
+    /// the name for nodes without a netlist name, the tag is skipped when `None`.
+    pub node_placeholder: Option<String>,
+    /// annotate the nets and symbols with the operating point, requires a netlist.
+    pub operating_point: Option<OperatingPoint>,
+    /// the significant digits of the operating point values.
+    pub op_precision: usize,
+    /// plot only the area or the symbols of the view.
+    pub view: Option<View>,
+    /// plot only the sub-circuit around a net or a component, requires a netlist.
+    pub subcircuit: Option<Subcircuit>,
+    /// the categories of elements that are not plotted.
+    pub hide: Hide,
+    /// style the symbols by library id, reference or property.
+    pub styles: Vec<StyleRule>,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            border: true,
+            theme: String::from("kicad_2000"),
+            image_type: ImageType::Svg,
+            paper: None,
+            library: SymbolLibrary::default(),
+            worksheet: None,
+            filename: None,
+            schema_path: None,
+            variables: HashMap::new(),
+            logo: None,
+            netclass_colors: HashMap::new(),
+            junctions: false,
+            dangling: false,
+            markers: Vec::new(),
+            net_colors: false,
+            net_color_map: HashMap::new(),
+            net_legend: false,
+            highlight: None,
+            node_names: true,
+            node_placeholder: None,
+            operating_point: None,
+            op_precision: 3,
+            view: None,
+            subcircuit: None,
+            hide: Hide::default(),
+            styles: Vec::new(),
+        }
+    }
+}
+
+/// Hide categories of elements in the plot, nothing is hidden by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hide {
+    /// hide all property fields.
+    pub fields: bool,
+    /// hide the property fields by name, like `Footprint` or `Datasheet`.
+    pub field_names: Vec<String>,
+    pub pin_numbers: bool,
+    pub pin_names: bool,
+    pub no_connects: bool,
+    /// hide the text notes and text boxes.
+    pub notes: bool,
+    pub power_symbols: bool,
+    /// hide the node names and operating point values.
+    pub netlist: bool,
+}
+
+impl Hide {
+    /// test if the property field is hidden.
+    pub fn field(&self, name: &str) -> bool {
+        self.fields
+            || self
+                .field_names
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// plot all pages of the schema, returns the page buffers and the warnings.
+pub fn plot_schema_options(
+    schema: &Schema,
+    netlist: Option<Netlist>,
+    options: &PlotOptions,
+) -> Result<(Vec<Vec<u8>>, Vec<Warning>), Error> {
+    plot_pages(schema, &netlist, options)
+}
+
+fn plot_pages(
+    schema: &Schema,
+    netlist: &Option<Netlist>,
+    options: &PlotOptions,
+) -> Result<(Vec<Vec<u8>>, Vec<Warning>), Error> {
+    let (pages, mut warnings) = page_items(schema, netlist, options)?;
+    let mut buffers = Vec::new();
+    for page in &pages {
+        buffers.push(render_page(page, options, &mut warnings)?);
+    }
+    Ok((buffers, warnings))
+}
+
+/// The plot items of a page.
+struct PageItems {
+    items: Vec<PlotItem>,
+    paper_size: (f64, f64),
+    /// the cropped area in mm.
+    view: Option<((f64, f64), (f64, f64))>,
+}
+
+/// collect the plot items of all pages.
+fn page_items(
+    schema: &Schema,
+    netlist: &Option<Netlist>,
+    options: &PlotOptions,
+) -> Result<(Vec<PageItems>, Vec<Warning>), Error> {
+    let theme = self::theme(&options.theme);
+    let mut warnings = theme.warnings().to_vec();
+    let library = options.library.resolve(schema, &mut warnings)?;
+    let sheets = border::sheets(schema, options.filename.as_deref().unwrap_or_default())?;
+    let mut project = Variables::new(&options.variables);
+    project.symbols(schema)?;
+    let mut pages = Vec::new();
+    let mut root_aliases = HashMap::new();
+    let selection = match (&options.subcircuit, netlist) {
+        (Some(subcircuit), Some(netlist)) => {
+            Some(subcircuit.select(&subcircuit::symbol_nets(schema, &library, netlist)?))
+        }
+        (Some(_), None) => {
+            warnings.push(Warning::MissingNetlist("sub-circuit"));
+            None
+        }
+        _ => None,
+    };
+    if options.net_colors && netlist.is_none() {
+        warnings.push(Warning::MissingNetlist("net colors"));
+    }
+    // the schema path of the options or the file name of the title block when it exists.
+    let schema_path = options.schema_path.clone().or_else(|| {
+        options
+            .filename
+            .clone()
+            .filter(|filename| std::path::Path::new(filename).is_file())
+    });
+    if schema_path.is_none() {
+        warnings.push(Warning::MissingSchemaFile);
+    }
+    for i in 0..schema.pages() {
+        //TODO: iterate page directly
+        let sheet = sheets.get(i).cloned().unwrap_or_default();
+        // images and other elements are read from the schema file.
+        let mut extra = if let Some(schema_path) = &schema_path {
+            let path = extra::page_file(schema_path, &sheet, i);
+            Extra::load(&path).unwrap_or_else(|err| {
+                warnings.push(Warning::FileLoad {
+                    path: path.display().to_string(),
+                    error: err.to_string(),
+                });
+                Extra::default()
+            })
+        } else {
+            Extra::default()
+        };
+        warnings.append(&mut extra.warnings);
+        // the bus aliases of the root page are known on all pages.
+        if i == 0 {
+            root_aliases = extra.bus_aliases.clone();
+        } else {
+            for (name, members) in &root_aliases {
+                extra
+                    .bus_aliases
+                    .entry(name.clone())
+                    .or_insert_with(|| members.clone());
+            }
+        }
+        // the paper of the options, the schema file or the schema model.
+        let (paper_name, paper_size) = match options.paper.as_ref().or(extra.paper.as_ref()) {
+            Some(paper) => paper_size(paper, &mut warnings),
+            None => None,
+        }
+        .map(|(name, size)| (name.to_string(), size))
+        .unwrap_or_else(|| {
+            let paper = &schema.pages[i].paper_size;
+            (paper.to_string(), paper.clone().into())
+        });
+        let variables = project.page(
+            &schema.pages[i].title_block,
+            &sheet,
+            &paper_name,
+            extra.version.as_deref(),
+        );
+        use self::schema::{PlotContext, PlotIterator};
+        let mut plot = schema.iter(i)?.plot(PlotContext {
+            schema,
+            title_block: &schema.pages[i].title_block,
+            paper_size,
+            page: i,
+            sheet,
+            variables,
+            extra: &extra,
+            theme: &theme,
+            netlist,
+            library: &library,
+            options,
+        });
+        plot.select(selection.clone());
+        let items = plot.by_ref().flatten().collect(); //TODO: plot all, remove clone
+        warnings.append(&mut plot.take_warnings());
+        let view = match &options.view {
+            Some(view) => {
+                let bounds = view.bounds(plot.view());
+                if bounds.is_none() {
+                    warnings.push(Warning::ViewNotFound(view.to_string(), i + 1));
+                }
+                bounds
+            }
+            None => None,
+        };
+        pages.push(PageItems {
+            items,
+            paper_size,
+            view,
+        });
+    }
+    Ok((pages, warnings))
+}
+
+/// plot the page items to a buffer.
+fn render_page(
+    page: &PageItems,
+    options: &PlotOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<u8>, Error> {
+    let buffer = SharedBuffer::default();
+    let mut cairo = CairoPlotter::new(&page.items, page.paper_size)?;
+    if let Some(view) = page.view {
+        cairo.crop(view);
+    }
+    cairo.plot(
+        Box::new(buffer.clone()),
+        options.border,
+        options.scale,
+        &options.image_type,
+    )?;
+    warnings.append(&mut cairo.take_warnings());
+    Ok(buffer.0.take())
+}
+
+/// plot the schema to the file or the plot buffers.
+///
+/// The elements read from the schema file, like images, graphics and netclass flags,
+/// are skipped with a warning, use `plot_schema_options` with the `schema_path`.
 pub fn plot_schema(
     schema: &Schema,
     filename: Option<&str>,
@@ -77,81 +439,34 @@ pub fn plot_schema(
     theme: &str,
     netlist: Option<Netlist>,
     image_type: Option<&str>,
-) -> Result<(), Error> {
+) -> Result<Vec<Warning>, Error> {
     let image_type = if let Some(image_type) = image_type {
-        if image_type == "pdf" {
-            Ok(ImageType::Pdf)
-        } else if image_type == "png" {
-            Ok(ImageType::Png)
-        } else if image_type == "svg" {
-            Ok(ImageType::Svg)
-        } else {
-            Err(Error::UnknownImageType(image_type.to_string()))
-        }
+        self::image_type(image_type)
     } else {
         Ok(ImageType::Svg)
     }?;
-    let theme = if theme == "mono" {
-        Theme::mono()
-    } else {
-        Theme::kicad_2000()
+    let options = PlotOptions {
+        scale,
+        border,
+        theme: theme.to_string(),
+        image_type,
+        ..Default::default()
     };
+    let (buffers, warnings) = plot_schema_options(schema, netlist, &options)?;
     if let Some(filename) = filename {
-        for i in 0..schema.pages() {
-            //TODO: iterate page directly
-            use self::schema::PlotIterator;
-            let iter = schema
-                .iter(i)?
-                .plot(
-                    schema,
-                    &schema.pages[i].title_block,
-                    schema.pages[i].paper_size.clone().into(),
-                    &theme,
-                    border,
-                    &netlist,
-                )
-                .flatten()
-                .collect(); //TODO: plot all, remove clone
-            let mut cairo = CairoPlotter::new(&iter);
-            check_directory(filename)?;
-            let out: Box<dyn Write> = Box::new(File::create(filename)?);
-            cairo.plot(out, border, scale, &image_type)?;
+        check_directory(filename)?;
+        for buffer in buffers {
+            File::create(filename)?.write_all(&buffer)?;
         }
     } else {
-        for i in 0..schema.pages() {
-            //TODO: iterate page directly
-            let mut rng = rand::thread_rng();
-            let num: u32 = rng.gen();
-            let filename = String::new() + temp_dir().to_str().unwrap() + "/" + &num.to_string(); //TODO: add
-                                                                                                  //extension
-
-            use self::schema::PlotIterator;
-            let iter = schema
-                .iter(i)?
-                .plot(
-                    schema,
-                    &schema.pages[i].title_block,
-                    schema.pages[i].paper_size.clone().into(),
-                    &theme,
-                    border,
-                    &netlist,
-                )
-                .flatten()
-                .collect(); //TODO: plot all, remove clone
-            let mut cairo = CairoPlotter::new(&iter);
-            let out: Box<dyn Write> = Box::new(File::create(&filename)?);
-            cairo.plot(out, border, scale, &image_type)?;
-
-            let mut f = File::open(&filename).expect("no file found");
-            let metadata = fs::metadata(&filename).expect("unable to read metadata");
-            let mut buffer = vec![0; metadata.len() as usize];
-            f.read_exact(&mut buffer).expect("buffer overflow");
+        for buffer in buffers {
             store_plot(buffer);
         }
     }
-    Ok(())
+    Ok(warnings)
 }
 
+/// plot the schema to buffers, the limitations of `plot_schema` apply.
 pub fn plot_schema_buffer(
     schema: &Schema,
     scale: f64,
@@ -159,56 +474,217 @@ pub fn plot_schema_buffer(
     theme: &str,
     netlist: Option<Netlist>,
     image_type: &str,
-) -> Result<Vec<Vec<u8>>, Error> {
-    let image_type = if image_type == "pdf" {
-        Ok(ImageType::Pdf)
-    } else if image_type == "png" {
-        Ok(ImageType::Png)
-    } else if image_type == "svg" {
-        Ok(ImageType::Svg)
+) -> Result<(Vec<Vec<u8>>, Vec<Warning>), Error> {
+    let options = PlotOptions {
+        scale,
+        border,
+        theme: theme.to_string(),
+        image_type: self::image_type(image_type)?,
+        ..Default::default()
+    };
+    plot_schema_options(schema, netlist, &options)
+}
+///plot a schematic of the circuit, the components are placed automatically.
+///
+/// The paper fits the drawing when no paper is set in the options.
+pub fn plot_circuit(
+    circuit: &Circuit,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    let theme = self::theme(&options.theme);
+    let mut warnings = theme.warnings().to_vec();
+    let (items, size) = circuit::layout(&circuit::components(circuit)?, &theme)?;
+    let paper_size = if options.paper.is_some() {
+        chart_paper(options, &mut warnings)
     } else {
-        Err(Error::UnknownImageType(image_type.to_string()))
-    }?;
+        size
+    };
+    warnings.append(&mut plot_items(items, paper_size, filename, options)?);
+    Ok(warnings)
+}
 
-    let theme = if theme == "mono" {
-        Theme::mono()
+/// the delay between the frames of an animation in ms.
+const FRAME_DELAY: u16 = 100;
+
+/// plot the transient simulation as an animated PNG of the first page.
+///
+/// The wires are colored by the node voltage at the times and the time cursor
+/// is drawn above the border or, without border, above the drawing. The page is
+/// plotted once, the frames only change the net colors.
+pub fn plot_transient(
+    schema: &Schema,
+    netlist: Option<Netlist>,
+    vectors: &HashMap<String, Vec<f64>>,
+    times: &[f64],
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    if options.image_type != ImageType::Png {
+        return Err(Error::AnimationImageType(format!(
+            "{:?}",
+            options.image_type
+        )));
+    }
+    let time = vectors
+        .get("time")
+        .ok_or_else(|| Error::VectorNotFound(String::from("time")))?;
+    let mut nodes: Vec<(String, Option<&Vec<f64>>)> = vectors
+        .iter()
+        .filter_map(|(name, values)| {
+            let name = name.to_lowercase();
+            if name == "time"
+                || name.contains('#')
+                || name.starts_with('@')
+                || name.starts_with("i(")
+            {
+                return None;
+            }
+            let node = name
+                .strip_prefix("v(")
+                .and_then(|n| n.strip_suffix(')'))
+                .map(str::to_string)
+                .unwrap_or(name);
+            Some((node, Some(values)))
+        })
+        .collect();
+    let range = times
+        .iter()
+        .flat_map(|t| {
+            nodes
+                .iter()
+                .filter_map(|(_, values)| values.and_then(|values| sample(time, values, *t)))
+        })
+        .fold((0.0f64, 0.0f64), |(min, max), v| (min.min(v), max.max(v)));
+    for ground in ["0", "gnd"] {
+        if !nodes.iter().any(|(name, _)| name == ground) {
+            nodes.push((ground.to_string(), None));
+        }
+    }
+    let start = time.first().copied().unwrap_or_default();
+    let end = time.last().copied().unwrap_or_default();
+
+    // plot the page once with a key color for every node.
+    let key = |index: usize| (0.0, 0.0, (index + 1) as f64 * 1e-9, 1.0);
+    let mut keyed = options.clone();
+    keyed.net_colors = true;
+    keyed.net_color_map = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| (name.clone(), key(index)))
+        .collect();
+    let (mut pages, mut warnings) = page_items(schema, &netlist, &keyed)?;
+    if pages.is_empty() {
+        return Ok(warnings);
+    }
+    let mut page = pages.swap_remove(0);
+    let mut colored = Vec::new();
+    for (item, plot_item) in page.items.iter_mut().enumerate() {
+        for (color, value) in colors_mut(plot_item).into_iter().enumerate() {
+            if let Some(node) = (0..nodes.len()).find(|index| {
+                let key = key(*index);
+                (value.0, value.1, value.2) == (key.0, key.1, key.2)
+            }) {
+                colored.push((item, color, node));
+            }
+        }
+    }
+
+    let theme = self::theme(&options.theme);
+    let bounds = if options.border {
+        None
     } else {
-        Theme::kicad_2000()
+        let bounds = CairoPlotter::new(&page.items, page.paper_size)?.bounds();
+        Some((
+            (bounds[[0, 0]], bounds[[0, 1]]),
+            (bounds[[1, 0]], bounds[[1, 1]]),
+        ))
     };
+    let bar = cursor_bar(page.paper_size, bounds);
+    // without the border all frames are plotted with the outline of the full cursor.
+    if !options.border && options.view.is_none() {
+        let count = page.items.len();
+        page.items
+            .append(&mut draw_cursor(1.0, "t = 0", bar, &theme)?);
+        let bounds = CairoPlotter::new(&page.items, page.paper_size)?.bounds();
+        page.view = Some((
+            (bounds[[0, 0]] - 2.54, bounds[[0, 1]] - 2.54),
+            (bounds[[1, 0]] + 2.54, bounds[[1, 1]] + 2.54),
+        ));
+        page.items.truncate(count);
+    }
 
-    let mut buffers = Vec::new();
-    for i in 0..schema.pages() {
-        //TODO: iterate page directly
-        let mut rng = rand::thread_rng();
-        let num: u32 = rng.gen();
-        let filename = String::new() + temp_dir().to_str().unwrap() + "/" + &num.to_string(); //TODO: add
-                                                                                              //extension
-
-        use self::schema::PlotIterator;
-        let iter = schema
-            .iter(i)?
-            .plot(
-                schema,
-                &schema.pages[i].title_block,
-                schema.pages[i].paper_size.clone().into(),
-                &theme,
-                border,
-                &netlist,
-            )
-            .flatten()
-            .collect(); //TODO: plot all, remove clone
-        let mut cairo = CairoPlotter::new(&iter);
-        let out: Box<dyn Write> = Box::new(File::create(&filename)?);
-        cairo.plot(out, border, scale, &image_type)?;
-
-        let mut f = File::open(&filename).expect("no file found");
-        let metadata = fs::metadata(&filename).expect("unable to read metadata");
-        let mut buffer = vec![0; metadata.len() as usize];
-        f.read_exact(&mut buffer).expect("buffer overflow");
-        buffers.push(buffer);
-    }
-    Ok(buffers)
+    let mut frames = Vec::new();
+    for t in times {
+        for (item, color, node) in &colored {
+            let (name, values) = &nodes[*node];
+            let value = match values {
+                Some(values) => sample(time, values, *t),
+                None => Some(0.0),
+            };
+            let (r, g, b, _) = match value {
+                Some(value) => voltage_color(value, range),
+                None => net_color(name, &options.net_color_map),
+            };
+            if let Some(color) = colors_mut(&mut page.items[*item]).into_iter().nth(*color) {
+                (color.0, color.1, color.2) = (r, g, b);
+            }
+        }
+        let position = if end > start {
+            (t - start) / (end - start)
+        } else {
+            0.0
+        };
+        let count = page.items.len();
+        let text = format!("t = {}", format_si(*t, "s", 3));
+        page.items
+            .append(&mut draw_cursor(position, &text, bar, &theme)?);
+        let mut frame_warnings = Vec::new();
+        frames.push(render_page(&page, options, &mut frame_warnings)?);
+        page.items.truncate(count);
+        for warning in frame_warnings {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    check_directory(filename)?;
+    File::create(filename)?.write_all(&animated_png(&frames)?)?;
+    Ok(warnings)
 }
+
+/// combine the PNG frames to an animated PNG.
+fn animated_png(frames: &[Vec<u8>]) -> Result<Vec<u8>, Error> {
+    let mut images = Vec::new();
+    for frame in frames {
+        let mut reader = png::Decoder::new(frame.as_slice()).read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+        data.truncate(info.buffer_size());
+        images.push((info, data));
+    }
+    let mut buffer = Vec::new();
+    if let Some((first, _)) = images.first() {
+        let mut encoder = png::Encoder::new(&mut buffer, first.width, first.height);
+        encoder.set_color(first.color_type);
+        encoder.set_depth(first.bit_depth);
+        encoder.set_animated(images.len() as u32, 0)?;
+        encoder.set_frame_delay(FRAME_DELAY, 1000)?;
+        let mut writer = encoder.write_header()?;
+        for (info, data) in &images {
+            if info.width != first.width || info.height != first.height {
+                return Err(Error::IoError(String::from(
+                    "the frames have different sizes",
+                )));
+            }
+            writer.write_image_data(data)?;
+        }
+        writer.finish()?;
+    }
+    Ok(buffer)
+}
+
 ///plot the pcb.
 pub fn plot_pcb(
     pcb: &Pcb,
@@ -216,7 +692,7 @@ pub fn plot_pcb(
     scale: f64,
     border: bool,
     theme: &str,
-) -> Result<(), Error> {
+) -> Result<Vec<Warning>, Error> {
     let image_type = if filename.ends_with(".svg") {
         ImageType::Svg
     } else if filename.ends_with(".png") {
@@ -224,18 +700,217 @@ pub fn plot_pcb(
     } else {
         ImageType::Pdf
     };
-    let theme = if theme == "mono" {
-        Theme::mono()
-    } else {
-        Theme::kicad_2000()
+    let options = PlotOptions {
+        scale,
+        border,
+        theme: theme.to_string(),
+        image_type,
+        ..Default::default()
+    };
+    plot_pcb_options(pcb, filename, &options)
+}
+
+///plot the pcb with the options.
+pub fn plot_pcb_options(
+    pcb: &Pcb,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    let theme = self::theme(&options.theme);
+    let mut warnings = theme.warnings().to_vec();
+    let (paper, paper_size) = match &options.paper {
+        Some(paper) => paper_size(paper, &mut warnings).unwrap_or(("A4", paper::A4)),
+        None => ("A4", paper::A4),
     };
 
     use self::pcb::PcbPlotIterator;
-    let iter = pcb.iter()?.plot(pcb, theme, border).flatten().collect();
-    let mut cairo = CairoPlotter::new(&iter); //TODO: set title block
+    let mut plot = pcb.iter()?.plot(
+        theme,
+        options.border,
+        paper,
+        paper_size,
+        options.worksheet.as_ref(),
+    );
+    let iter = plot.by_ref().flatten().collect();
+    warnings.append(&mut plot.take_warnings());
+    let mut cairo = CairoPlotter::new(&iter, paper_size)?; //TODO: set title block
 
     check_directory(filename)?;
     let out: Box<dyn Write> = Box::new(File::create(filename)?);
-    cairo.plot(out, border, scale, &image_type)?;
-    Ok(())
+    cairo.plot(out, options.border, options.scale, &options.image_type)?;
+    warnings.append(&mut cairo.take_warnings());
+    Ok(warnings)
+}
+
+/// margin between the paper edge and the chart.
+const CHART_MARGIN: f64 = 10.0;
+
+///plot the chart of the simulation results with the options.
+pub fn plot_chart(
+    chart: &Chart,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    let theme = self::theme(&options.theme);
+    let mut warnings = theme.warnings().to_vec();
+    let paper_size = chart_paper(options, &mut warnings);
+    let items = chart.plot(chart_area(paper_size), &theme)?;
+    warnings.append(&mut plot_items(items, paper_size, filename, options)?);
+    Ok(warnings)
+}
+
+///plot the Bode plot of an AC analysis with the options.
+pub fn plot_bode(
+    bode: &Bode,
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    let theme = self::theme(&options.theme);
+    let mut warnings = theme.warnings().to_vec();
+    let paper_size = chart_paper(options, &mut warnings);
+    let items = bode.plot(chart_area(paper_size), &theme)?;
+    warnings.append(&mut plot_items(items, paper_size, filename, options)?);
+    Ok(warnings)
+}
+
+/// get the paper for a chart, A4 when not set in the options.
+fn chart_paper(options: &PlotOptions, warnings: &mut Vec<Warning>) -> (f64, f64) {
+    match &options.paper {
+        Some(paper) => paper_size(paper, warnings).map_or(paper::A4, |(_, size)| size),
+        None => paper::A4,
+    }
+}
+
+/// get the name and the size of the paper, unknown papers are reported.
+fn paper_size(paper: &str, warnings: &mut Vec<Warning>) -> Option<(&'static str, (f64, f64))> {
+    let size = paper::parse(paper);
+    if size.is_none() {
+        warnings.push(Warning::UnknownPaper(paper.to_string()));
+    }
+    size
+}
+
+fn chart_area(paper_size: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+    (
+        (CHART_MARGIN, CHART_MARGIN),
+        (paper_size.0 - CHART_MARGIN, paper_size.1 - CHART_MARGIN),
+    )
+}
+
+/// plot the items to the file.
+fn plot_items(
+    items: Vec<PlotItem>,
+    paper_size: (f64, f64),
+    filename: &str,
+    options: &PlotOptions,
+) -> Result<Vec<Warning>, Error> {
+    let mut cairo = CairoPlotter::new(&items, paper_size)?;
+    check_directory(filename)?;
+    let out: Box<dyn Write> = Box::new(File::create(filename)?);
+    cairo.plot(out, options.border, options.scale, &options.image_type)?;
+    Ok(cairo.take_warnings())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{animated_png, plot_transient, Error, ImageType, PlotOptions};
+    use elektron_sexp::Schema;
+    use elektron_spice::Netlist;
+    use std::collections::HashMap;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut encoder = png::Encoder::new(&mut buffer, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&vec![0; (width * height * 3) as usize])
+            .unwrap();
+        writer.finish().unwrap();
+        buffer
+    }
+
+    #[test]
+    fn animation() {
+        let buffer = animated_png(&[png(10, 5), png(10, 5)]).unwrap();
+        let reader = png::Decoder::new(buffer.as_slice()).read_info().unwrap();
+        assert_eq!((10, 5), (reader.info().width, reader.info().height));
+        assert_eq!(2, reader.info().animation_control.unwrap().num_frames);
+        assert!(animated_png(&[png(10, 5), png(12, 5)]).is_err());
+    }
+    #[test]
+    fn transient_frames() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let vectors = HashMap::from([
+            (String::from("time"), vec![0.0, 0.5e-3, 1.0e-3]),
+            (String::from("v(out)"), vec![-10.0, 0.0, 10.0]),
+        ]);
+        let options = PlotOptions {
+            border: false,
+            image_type: ImageType::Png,
+            ..PlotOptions::default()
+        };
+        plot_transient(
+            &doc,
+            None,
+            &vectors,
+            &[0.0, 0.333e-3, 1.0e-3],
+            "/tmp/summe-transient.png",
+            &options,
+        )
+        .unwrap();
+        let file = std::fs::File::open("/tmp/summe-transient.png").unwrap();
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        assert_eq!(3, reader.info().animation_control.unwrap().num_frames);
+
+        let options = PlotOptions {
+            image_type: ImageType::Svg,
+            ..PlotOptions::default()
+        };
+        assert!(matches!(
+            plot_transient(
+                &doc,
+                None,
+                &vectors,
+                &[0.0],
+                "/tmp/summe-transient.svg",
+                &options
+            ),
+            Err(Error::AnimationImageType(_))
+        ));
+    }
+    #[test]
+    fn transient_colors() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let netlist = Netlist::from(&doc).unwrap();
+        let vectors = HashMap::from([
+            (String::from("time"), vec![0.0, 1.0e-3]),
+            (String::from("v(out)"), vec![-10.0, 10.0]),
+        ]);
+        let options = PlotOptions {
+            image_type: ImageType::Png,
+            ..PlotOptions::default()
+        };
+        plot_transient(
+            &doc,
+            Some(netlist),
+            &vectors,
+            &[0.0, 1.0e-3],
+            "/tmp/summe-transient-colors.png",
+            &options,
+        )
+        .unwrap();
+        let file = std::fs::File::open("/tmp/summe-transient-colors.png").unwrap();
+        let mut reader = png::Decoder::new(file).read_info().unwrap();
+        let mut first = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut first).unwrap();
+        let mut last = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut last).unwrap();
+        // the output net is blue at -10 V and red at 10 V.
+        let samples = reader.output_color_type().0.samples();
+        let has =
+            |data: &[u8], color: [u8; 3]| data.chunks(samples).any(|pixel| pixel[..3] == color);
+        assert!(has(&first, [0, 0, 255]) && !has(&first, [255, 0, 0]));
+        assert!(has(&last, [255, 0, 0]) && !has(&last, [0, 0, 255]));
+    }
 }
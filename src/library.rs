@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Warning};
+use elektron_sexp::parser::SexpParser;
+use elektron_sexp::{LibraryIterator, LibrarySymbol, Schema, SchemaElement};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref LIB: Regex =
+        Regex::new(r#"\(lib\s+\(name\s+"([^"]*)"\).*?\(uri\s+"([^"]*)"\)"#).unwrap();
+    static ref VARIABLE: Regex = Regex::new(r"\$\{([^}]+)\}").unwrap();
+}
+
+/// environment variables pointing to the KiCad symbol libraries.
+const SYMBOL_DIRS: [&str; 4] = [
+    "KICAD_SYMBOL_DIR",
+    "KICAD8_SYMBOL_DIR",
+    "KICAD7_SYMBOL_DIR",
+    "KICAD6_SYMBOL_DIR",
+];
+
+/// Search paths for external `.kicad_sym` libraries.
+///
+/// Symbols missing in the `lib_symbols` of the schema are loaded from these paths,
+/// the library `Device` is the file `Device.kicad_sym` in one of the paths or the
+/// uri of the nickname in a `sym-lib-table`.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolLibrary {
+    paths: Vec<String>,
+    /// the nickname and the uri of the libraries in the tables.
+    tables: Vec<(String, String)>,
+}
+
+impl SymbolLibrary {
+    pub fn new(paths: Vec<String>) -> Self {
+        Self {
+            paths,
+            tables: Vec::new(),
+        }
+    }
+    /// create the search paths from the KiCad symbol directory environment variables.
+    pub fn from_env() -> Self {
+        let mut library = Self::default();
+        for var in SYMBOL_DIRS {
+            if let Ok(path) = std::env::var(var) {
+                library.add_path(&path);
+            }
+        }
+        library
+    }
+    pub fn add_path(&mut self, path: &str) {
+        if !self.paths.iter().any(|p| p == path) {
+            self.paths.push(path.to_string());
+        }
+    }
+    /// add the libraries from a `sym-lib-table` file.
+    pub fn add_table(&mut self, filename: &str) -> Result<(), Error> {
+        let content = std::fs::read_to_string(filename)?;
+        let project = Path::new(filename)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for library in table_libraries(&content, &project) {
+            if !self.tables.contains(&library) {
+                self.tables.push(library);
+            }
+        }
+        Ok(())
+    }
+    pub fn paths(&self) -> &Vec<String> {
+        &self.paths
+    }
+    /// get the first existing file of the library.
+    fn file(&self, nickname: &str) -> Option<PathBuf> {
+        self.tables
+            .iter()
+            .filter(|(name, _)| name == nickname)
+            .map(|(_, uri)| PathBuf::from(uri))
+            .chain(
+                self.paths
+                    .iter()
+                    .map(|path| Path::new(path).join(format!("{}.kicad_sym", nickname))),
+            )
+            .find(|file| file.is_file())
+    }
+    /// load the symbols of the library file.
+    fn load(&self, nickname: &str, warnings: &mut Vec<Warning>) -> HashMap<String, LibrarySymbol> {
+        let mut symbols = HashMap::new();
+        let file = match self.file(nickname) {
+            Some(file) => file,
+            None => {
+                warnings.push(Warning::LibraryNotFound(nickname.to_string()));
+                return symbols;
+            }
+        };
+        match SexpParser::load(&file.to_string_lossy()) {
+            Ok(doc) => {
+                for symbol in doc.iter().node() {
+                    symbols.insert(format!("{}:{}", nickname, symbol.lib_id), symbol);
+                }
+            }
+            Err(err) => warnings.push(Warning::LibraryLoad {
+                path: file.display().to_string(),
+                error: err.to_string(),
+            }),
+        }
+        symbols
+    }
+    /// load the symbols used in the schema but not contained in the `lib_symbols`.
+    ///
+    /// Missing library files are reported and the symbols are drawn as placeholders.
+    /// Derived symbols are merged with the symbol they extend.
+    pub(crate) fn resolve(
+        &self,
+        schema: &Schema,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<HashMap<String, LibrarySymbol>, Error> {
+        let mut symbols: HashMap<String, LibrarySymbol> = HashMap::new();
+        if self.paths.is_empty() && self.tables.is_empty() {
+            return Ok(symbols);
+        }
+        let mut libraries: HashMap<String, HashMap<String, LibrarySymbol>> = HashMap::new();
+        for page in 0..schema.pages() {
+            for element in schema.iter(page)? {
+                if let SchemaElement::Symbol(symbol) = element {
+                    if schema.get_library(&symbol.lib_id).is_some()
+                        || symbols.contains_key(&symbol.lib_id)
+                    {
+                        continue;
+                    }
+                    let nickname = symbol.lib_id.split(':').next().unwrap_or_default();
+                    let library = libraries
+                        .entry(nickname.to_string())
+                        .or_insert_with(|| self.load(nickname, warnings));
+                    if let Some(lib) = library.get(&symbol.lib_id) {
+                        match derive(library, nickname, lib) {
+                            Ok(lib) => {
+                                symbols.insert(symbol.lib_id.clone(), lib);
+                            }
+                            Err(parent) => {
+                                warnings.push(Warning::MissingLibrarySymbol(parent));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(symbols)
+    }
+}
+
+/// merge a derived symbol with the symbol it extends.
+///
+/// The parent gives the units, graphics and pins, the properties of the derived symbol
+/// replace the parent properties with the same key. The error is the id of the missing parent.
+fn derive(
+    library: &HashMap<String, LibrarySymbol>,
+    nickname: &str,
+    symbol: &LibrarySymbol,
+) -> Result<LibrarySymbol, String> {
+    let mut symbol = symbol.clone();
+    let mut chain = vec![symbol.lib_id.clone()];
+    while !symbol.extends.is_empty() {
+        let parent_id = format!("{}:{}", nickname, symbol.extends);
+        let parent = match library.get(&parent_id) {
+            Some(parent) if !chain.contains(&parent.lib_id) => parent,
+            _ => return Err(parent_id),
+        };
+        chain.push(parent.lib_id.clone());
+        let mut property = parent.property.clone();
+        for prop in symbol.property.drain(..) {
+            match property.iter_mut().find(|p| p.key == prop.key) {
+                Some(p) => *p = prop,
+                None => property.push(prop),
+            }
+        }
+        symbol.property = property;
+        symbol.pin_numbers_show = parent.pin_numbers_show;
+        symbol.pin_names_show = parent.pin_names_show;
+        symbol.pin_names_offset = parent.pin_names_offset;
+        symbol.power = parent.power;
+        symbol.graph = parent.graph.clone();
+        symbol.pin = parent.pin.clone();
+        symbol.symbols = parent
+            .symbols
+            .iter()
+            .map(|unit| {
+                let mut unit = unit.clone();
+                unit.lib_id = unit.lib_id.replacen(&parent.lib_id, &symbol.lib_id, 1);
+                unit
+            })
+            .collect();
+        symbol.extends = parent.extends.clone();
+    }
+    Ok(symbol)
+}
+
+/// get the nickname and the uri of the libraries from the content of a `sym-lib-table`.
+///
+/// Variables in the uri are taken from the environment, `KIPRJMOD` is the directory of the table.
+fn table_libraries(content: &str, project: &str) -> Vec<(String, String)> {
+    LIB.captures_iter(content)
+        .map(|cap| {
+            let uri = VARIABLE.replace_all(&cap[2], |caps: &regex::Captures| {
+                if &caps[1] == "KIPRJMOD" {
+                    project.to_string()
+                } else {
+                    std::env::var(&caps[1]).unwrap_or_default()
+                }
+            });
+            (cap[1].to_string(), uri.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use elektron_sexp::parser::SexpParser;
+    use elektron_sexp::{LibraryIterator, LibrarySymbol, Schema};
+
+    use super::{derive, table_libraries, SymbolLibrary};
+    use crate::error::Warning;
+
+    #[test]
+    fn sym_lib_table() {
+        let content = r#"(sym_lib_table
+            (lib (name "Device")(type "KiCad")(uri "/usr/share/kicad/symbols/Device.kicad_sym")(options "")(descr ""))
+            (lib (name "project")(type "KiCad")(uri "${KIPRJMOD}/lib/symbols.kicad_sym")(options "")(descr ""))
+        )"#;
+        assert_eq!(
+            vec![
+                (
+                    String::from("Device"),
+                    String::from("/usr/share/kicad/symbols/Device.kicad_sym")
+                ),
+                (
+                    String::from("project"),
+                    String::from("/home/user/project/lib/symbols.kicad_sym")
+                ),
+            ],
+            table_libraries(content, "/home/user/project")
+        );
+    }
+    #[test]
+    fn resolve() {
+        // move the resistor from the lib_symbols to a library file, remove the ground symbol.
+        let content = std::fs::read_to_string("files/dco.kicad_sch").unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        let block = |name: &str| {
+            let start = lines
+                .iter()
+                .position(|l| l.starts_with(&format!("    (symbol \"{}\"", name)))
+                .unwrap();
+            let end = start
+                + 1
+                + lines[start + 1..]
+                    .iter()
+                    .position(|l| l.starts_with("    (symbol ") || l.starts_with("  )"))
+                    .unwrap();
+            (start, end)
+        };
+        let (resistor, ground) = (block("Device:R"), block("power:GND"));
+        let dir = std::env::temp_dir().join(format!("elektron-library-{}", std::process::id()));
+        let (empty, libs) = (dir.join("empty"), dir.join("libs"));
+        std::fs::create_dir_all(&empty).unwrap();
+        std::fs::create_dir_all(&libs).unwrap();
+        std::fs::write(
+            libs.join("Device.kicad_sym"),
+            format!(
+                "(kicad_symbol_lib (version 20211014) (generator test)\n{}\n)",
+                lines[resistor.0..resistor.1]
+                    .join("\n")
+                    .replacen("\"Device:R\"", "\"R\"", 1)
+            ),
+        )
+        .unwrap();
+        let schema: Vec<&str> = lines
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                !(resistor.0..resistor.1).contains(i) && !(ground.0..ground.1).contains(i)
+            })
+            .map(|(_, l)| *l)
+            .collect();
+        let filename = dir.join("dco.kicad_sch");
+        std::fs::write(&filename, schema.join("\n")).unwrap();
+        let schema = Schema::load(&filename.to_string_lossy()).unwrap();
+
+        let library = SymbolLibrary::new(vec![
+            empty.to_string_lossy().to_string(),
+            libs.to_string_lossy().to_string(),
+        ]);
+        let mut warnings = Vec::new();
+        let symbols = library.resolve(&schema, &mut warnings).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(symbols.contains_key("Device:R"));
+        assert_eq!(1, symbols.len());
+        assert_eq!(
+            vec![Warning::LibraryNotFound(String::from("power"))],
+            warnings
+        );
+    }
+    #[test]
+    fn derived() {
+        let content = std::fs::read_to_string("files/dco.kicad_sch").unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        let start = lines
+            .iter()
+            .position(|l| l.starts_with("    (symbol \"Device:R\""))
+            .unwrap();
+        let end = start
+            + 1
+            + lines[start + 1..]
+                .iter()
+                .position(|l| l.starts_with("    (symbol "))
+                .unwrap();
+        let library = format!(
+            r#"(kicad_symbol_lib (version 20211014) (generator test)
+{}
+    (symbol "R_US" (extends "R")
+      (property "Reference" "R" (id 0) (at 2.032 0 90)
+        (effects (font (size 1.27 1.27)))
+      )
+      (property "Value" "R_US" (id 1) (at 0 0 90)
+        (effects (font (size 1.27 1.27)))
+      )
+    )
+    (symbol "C_Polarized" (extends "C")
+      (property "Value" "C_Polarized" (id 1) (at 0 0 90)
+        (effects (font (size 1.27 1.27)))
+      )
+    )
+)"#,
+            lines[start..end]
+                .join("\n")
+                .replacen("\"Device:R\"", "\"R\"", 1)
+        );
+        let doc = SexpParser::from(library);
+        let symbols: HashMap<String, LibrarySymbol> = doc
+            .iter()
+            .node()
+            .map(|symbol: LibrarySymbol| (format!("Device:{}", symbol.lib_id), symbol))
+            .collect();
+
+        let symbol = derive(&symbols, "Device", &symbols["Device:R_US"]).unwrap();
+        assert_eq!("R_US", symbol.lib_id);
+        assert_eq!(2, symbol.symbols.len());
+        assert_eq!(
+            vec!["R_US_0_1", "R_US_1_1"],
+            symbol
+                .symbols
+                .iter()
+                .map(|u| u.lib_id.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(2, symbol.symbols[1].pin.len());
+        assert!(!symbol.pin_numbers_show);
+        let value = symbol.property.iter().find(|p| p.key == "Value").unwrap();
+        assert_eq!("R_US", value.value);
+        assert!(symbol.property.iter().any(|p| p.key == "Footprint"));
+        assert_eq!(
+            Err(String::from("Device:C")),
+            derive(&symbols, "Device", &symbols["Device:C_Polarized"])
+        );
+    }
+}
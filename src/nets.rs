@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::cairo_plotter::PlotItem;
+use crate::error::Error;
+use regex::Regex;
+
+/// z order of the highlighted items, they are drawn on top.
+pub const HIGHLIGHT_Z: usize = 100;
+
+/// distinct colors for the nets without a user color.
+pub(crate) const PALETTE: [(f64, f64, f64, f64); 12] = [
+    (0.894, 0.102, 0.110, 1.0),
+    (0.216, 0.494, 0.722, 1.0),
+    (0.302, 0.686, 0.290, 1.0),
+    (0.596, 0.306, 0.639, 1.0),
+    (1.000, 0.498, 0.000, 1.0),
+    (0.651, 0.337, 0.157, 1.0),
+    (0.969, 0.506, 0.749, 1.0),
+    (0.000, 0.545, 0.545, 1.0),
+    (0.737, 0.741, 0.133, 1.0),
+    (0.090, 0.745, 0.812, 1.0),
+    (0.400, 0.400, 0.800, 1.0),
+    (0.800, 0.200, 0.400, 1.0),
+];
+
+/// get the color of the net, the colors of the user map are used first.
+///
+/// The palette color is selected by a hash of the name, so a net has the
+/// same color on all pages.
+pub fn net_color(
+    name: &str,
+    colors: &HashMap<String, (f64, f64, f64, f64)>,
+) -> (f64, f64, f64, f64) {
+    if let Some(color) = colors
+        .get(name)
+        .or_else(|| colors.get(&name.to_lowercase()))
+    {
+        return *color;
+    }
+    // FNV-1a, the std hasher is not stable between releases.
+    let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+/// Emphasize the nets selected by name or by a regular expression.
+///
+/// The highlighted nets are drawn in the highlight color with a wider line,
+/// all other items are dimmed.
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pub nets: Vec<String>,
+    pub pattern: Option<Regex>,
+    pub color: (f64, f64, f64, f64),
+    /// the line width of the highlighted nets is multiplied by this factor.
+    pub width: f64,
+    /// the alpha factor of the dimmed items.
+    pub dim: f64,
+}
+
+impl Highlight {
+    pub fn new(nets: Vec<String>) -> Self {
+        Self {
+            nets,
+            pattern: None,
+            color: (1.0, 0.5, 0.0, 1.0),
+            width: 2.0,
+            dim: 0.3,
+        }
+    }
+    /// select the nets with a regular expression.
+    pub fn pattern(pattern: &str) -> Result<Self, Error> {
+        let mut highlight = Self::new(Vec::new());
+        highlight.pattern = Some(
+            Regex::new(pattern)
+                .map_err(|err| Error::Pattern(pattern.to_string(), err.to_string()))?,
+        );
+        Ok(highlight)
+    }
+    pub fn matches(&self, name: &str) -> bool {
+        self.nets.iter().any(|n| n == name)
+            || self
+                .pattern
+                .as_ref()
+                .map(|p| p.is_match(name))
+                .unwrap_or(false)
+    }
+    /// dim the item when it is not highlighted.
+    pub fn dim(&self, item: PlotItem) -> PlotItem {
+        let z = match &item {
+            PlotItem::Arc(z, _)
+            | PlotItem::Bezier(z, _)
+            | PlotItem::Circle(z, _)
+            | PlotItem::Image(z, _)
+            | PlotItem::Line(z, _)
+            | PlotItem::Polyline(z, _)
+            | PlotItem::Rectangle(z, _)
+            | PlotItem::Text(z, _) => *z,
+        };
+        if z == HIGHLIGHT_Z {
+            item
+        } else {
+            fade(item, self.dim)
+        }
+    }
+}
+
+/// multiply the alpha of the item colors, images are not changed.
+pub(crate) fn fade(item: PlotItem, alpha: f64) -> PlotItem {
+    let dim = |color: &mut (f64, f64, f64, f64)| color.3 *= alpha;
+    let dim_fill = |fill: &mut Option<(f64, f64, f64, f64)>| {
+        if let Some(fill) = fill {
+            fill.3 *= alpha;
+        }
+    };
+    match item {
+        PlotItem::Arc(z, mut arc) => {
+            dim(&mut arc.color);
+            dim_fill(&mut arc.fill);
+            PlotItem::Arc(z, arc)
+        }
+        PlotItem::Bezier(z, mut bezier) => {
+            dim(&mut bezier.color);
+            dim_fill(&mut bezier.fill);
+            PlotItem::Bezier(z, bezier)
+        }
+        PlotItem::Circle(z, mut circle) => {
+            dim(&mut circle.color);
+            dim_fill(&mut circle.fill);
+            PlotItem::Circle(z, circle)
+        }
+        PlotItem::Line(z, mut line) => {
+            dim(&mut line.color);
+            PlotItem::Line(z, line)
+        }
+        PlotItem::Polyline(z, mut polyline) => {
+            dim(&mut polyline.color);
+            dim_fill(&mut polyline.fill);
+            PlotItem::Polyline(z, polyline)
+        }
+        PlotItem::Rectangle(z, mut rectangle) => {
+            dim(&mut rectangle.color);
+            dim_fill(&mut rectangle.fill);
+            PlotItem::Rectangle(z, rectangle)
+        }
+        PlotItem::Text(z, mut text) => {
+            dim(&mut text.color);
+            PlotItem::Text(z, text)
+        }
+        item => item,
+    }
+}
+
+/// get the colors of the item, the fill color follows the stroke color.
+pub(crate) fn colors_mut(item: &mut PlotItem) -> Vec<&mut (f64, f64, f64, f64)> {
+    let (color, fill) = match item {
+        PlotItem::Arc(_, arc) => (&mut arc.color, arc.fill.as_mut()),
+        PlotItem::Bezier(_, bezier) => (&mut bezier.color, bezier.fill.as_mut()),
+        PlotItem::Circle(_, circle) => (&mut circle.color, circle.fill.as_mut()),
+        PlotItem::Line(_, line) => (&mut line.color, None),
+        PlotItem::Polyline(_, polyline) => (&mut polyline.color, polyline.fill.as_mut()),
+        PlotItem::Rectangle(_, rectangle) => (&mut rectangle.color, rectangle.fill.as_mut()),
+        PlotItem::Text(_, text) => (&mut text.color, None),
+        PlotItem::Image(..) => return Vec::new(),
+    };
+    std::iter::once(color).chain(fill).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{net_color, Highlight, HIGHLIGHT_Z};
+    use crate::cairo_plotter::{Line, LineCap, PlotItem};
+    use ndarray::arr2;
+
+    #[test]
+    fn colors() {
+        let mut colors = HashMap::new();
+        colors.insert(String::from("GND"), (0.5, 0.5, 0.5, 1.0));
+        assert_eq!((0.5, 0.5, 0.5, 1.0), net_color("GND", &colors));
+        assert_eq!(net_color("OUT", &colors), net_color("OUT", &HashMap::new()));
+    }
+    #[test]
+    fn highlight() {
+        let highlight = Highlight::pattern("^/?VCC").unwrap();
+        assert!(highlight.matches("VCC"));
+        assert!(highlight.matches("/VCC_5V"));
+        assert!(!highlight.matches("GND"));
+        assert!(Highlight::new(vec![String::from("GND")]).matches("GND"));
+        assert!(Highlight::pattern("(").is_err());
+
+        let line = |z| {
+            PlotItem::Line(
+                z,
+                Line::new(
+                    arr2(&[[0.0, 0.0], [1.0, 1.0]]),
+                    0.254,
+                    String::from("default"),
+                    LineCap::Butt,
+                    (0.0, 0.0, 0.0, 1.0),
+                ),
+            )
+        };
+        if let PlotItem::Line(_, line) = highlight.dim(line(10)) {
+            assert_eq!(0.3, line.color.3);
+        }
+        if let PlotItem::Line(_, line) = highlight.dim(line(HIGHLIGHT_Z)) {
+            assert_eq!(1.0, line.color.3);
+        }
+    }
+}
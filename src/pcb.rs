@@ -1,7 +1,11 @@
+use super::border::{draw_border, SheetInfo};
 use super::cairo_plotter::{Circle, Line, LineCap, PlotItem, Text};
 use super::theme::{Theme, Themer, ThemerMerge};
+use crate::error::Warning;
 use crate::text;
-use elektron_sexp::{PcbElements, Stroke, Pcb, Shape, Transform};
+use crate::variables::Variables;
+use crate::worksheet::Worksheet;
+use elektron_sexp::{PcbElements, Stroke, Shape, Transform};
 use ndarray::arr2;
 
 macro_rules! theme {
@@ -12,7 +16,9 @@ macro_rules! theme {
                 linetype: "default".to_string(),
                 color: (0.0, 0.0, 0.0, 0.0),
             },
-            &$self.theme.stroke(&$element.layer).unwrap(),
+            &$self
+                .theme
+                .stroke_or_default(&$element.layer, &mut $self.warnings),
         )
     };
 }
@@ -21,7 +27,10 @@ pub struct PcbPlot<'a, I> {
     iter: I,
     theme: Theme,
     border: bool,
-    pcb: &'a Pcb,
+    paper: &'a str,
+    paper_size: (f64, f64),
+    worksheet: Option<&'a Worksheet>,
+    warnings: Vec<Warning>,
 }
 
 impl<'a, I> Iterator for PcbPlot<'a, I>
@@ -30,6 +39,31 @@ where
 {
     type Item = Vec<PlotItem>;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.border {
+            self.border = false;
+            let variables =
+                Variables::default().page(&None, &SheetInfo::default(), self.paper, None);
+            if let Some(worksheet) = self.worksheet {
+                return Some(worksheet.plot(
+                    self.paper_size,
+                    &variables,
+                    1,
+                    &self.theme,
+                    &mut self.warnings,
+                ));
+            }
+            match draw_border(
+                &None,
+                &SheetInfo::default(),
+                &variables,
+                None,
+                self.paper_size,
+                &self.theme,
+            ) {
+                Ok(border) => return Some(border),
+                Err(err) => self.warnings.push(Warning::from_error("border", err)),
+            }
+        }
         loop {
             match self.iter.next() {
                 Some(PcbElements::Line(line)) => {
@@ -73,11 +107,11 @@ where
                                 if !text.hidden {
                                     let effects = Themer::get(
                                         &text.effects,
-                                        &self.theme.effects("footprint").unwrap(),
+                                        &self
+                                            .theme
+                                            .effects_or_default("footprint", &mut self.warnings),
                                     );
-                                    let angle = if let Some(angle) = text.angle {
-                                        angle 
-                                    } else { 0.0 };
+                                    let angle = text.angle.unwrap_or(0.0);
                                     graphics.push(text!(
                                         Shape::transform(footprint, &text.at),
                                         angle,
@@ -121,7 +155,10 @@ where
                                     ),
                                 ));
                             }
-                            elektron_sexp::Graphics::FpArc(_) => {}
+                            elektron_sexp::Graphics::FpArc(_) => {
+                                self.warnings
+                                    .push(Warning::UnsupportedElement(String::from("fp_arc")));
+                            }
                         }
                     }
                     return Some(graphics);
@@ -136,19 +173,40 @@ where
 }
 
 impl<'a, I> PcbPlot<'a, I> {
-    pub fn new(iter: I, pcb: &'a Pcb, theme: Theme, border: bool) -> Self {
+    pub fn new(
+        iter: I,
+        theme: Theme,
+        border: bool,
+        paper: &'a str,
+        paper_size: (f64, f64),
+        worksheet: Option<&'a Worksheet>,
+    ) -> Self {
         Self {
             iter,
-            pcb,
             border,
             theme,
+            paper,
+            paper_size,
+            worksheet,
+            warnings: Vec::new(),
         }
     }
+    /// take the warnings collected while plotting.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
 }
 
 pub trait PcbPlotIterator<T>: Iterator<Item = T> + Sized {
-    fn plot(self, pcb: &'_ Pcb, theme: Theme, border: bool) -> PcbPlot<Self> {
-        PcbPlot::new(self, pcb, theme, border)
+    fn plot<'a>(
+        self,
+        theme: Theme,
+        border: bool,
+        paper: &'a str,
+        paper_size: (f64, f64),
+        worksheet: Option<&'a Worksheet>,
+    ) -> PcbPlot<'a, Self> {
+        PcbPlot::new(self, theme, border, paper, paper_size, worksheet)
     }
 }
 impl<T, I: Iterator<Item = T>> PcbPlotIterator<T> for I {}
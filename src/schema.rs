@@ -1,12 +1,51 @@
+use std::collections::HashMap;
+
 use elektron_spice::{Netlist, Point};
+use itertools::Itertools;
 use ndarray::{arr1, arr2, Array1, Array2};
 
-use super::border::draw_border;
+use super::border::{draw_border, draw_legend, legend_bounds, Corner, LegendEntry, SheetInfo};
 use super::cairo_plotter::{Circle, Line, LineCap, PlotItem, Text};
-use super::theme::{Theme, Themer, ThemerMerge};
-use crate::cairo_plotter::{Arc, Polyline, Rectangle};
-use crate::text;
-use elektron_sexp::{Graph, SchemaElement, TitleBlock, Schema, Shape, Transform, Symbol, Pin};
+use super::theme::{Theme, Themer, ThemerMerge, ThemerStyle};
+use crate::bus;
+use crate::cairo_plotter::{outline, points, text_width, Arc, Bezier, Polyline, Rectangle};
+use crate::connections::{on_wire, place_tag, same, Connections, Wire};
+use crate::error::Warning;
+use crate::extra::{Extra, Fill, Graphic};
+use crate::nets::{fade, net_color, Highlight, HIGHLIGHT_Z};
+use crate::simulation::format_si;
+use crate::style::Style;
+use crate::subcircuit::{selected_pins, Selection};
+use crate::variables::Variables;
+use crate::{text, PlotOptions};
+use elektron_sexp::{
+    BusEntry, Effects, Graph, LibrarySymbol, Pin, Schema, SchemaElement, Shape, Stroke, Symbol,
+    TitleBlock, Transform,
+};
+
+/// size of the shape at the end of a netclass flag.
+const FLAG_SIZE: f64 = 1.27;
+/// radius of the junction dots.
+const JUNCTION_RADIUS: f64 = 0.35;
+/// radius of the circles marking dangling ends.
+const DANGLING_RADIUS: f64 = 0.3;
+/// the arrow of the ERC markers like KiCad, the tip is at the marker position.
+const MARKER_SHAPE: [[f64; 2]; 9] = [
+    [0.0, 0.0],
+    [8.0, 1.0],
+    [4.0, 3.0],
+    [13.0, 8.0],
+    [9.0, 9.0],
+    [8.0, 13.0],
+    [3.0, 4.0],
+    [1.0, 8.0],
+    [0.0, 0.0],
+];
+/// size of a marker shape unit in mm.
+const MARKER_SCALE: f64 = 0.15;
+
+/// A label with the resolved text, the color and the z order.
+type Label = (String, Option<(f64, f64, f64, f64)>, usize);
 
 macro_rules! get_effects {
     ($orig:expr, $theme:expr) => {
@@ -25,7 +64,35 @@ pub struct SchemaPlot<'a, I> {
     schema: &'a Schema,
     title_block: &'a Option<TitleBlock>,
     paper_size: (f64, f64),
+    /// the index of the page in the schema.
+    page: usize,
+    sheet: SheetInfo,
+    variables: Variables,
+    extra: &'a Extra,
+    graphics: bool,
     netlist: &'a Option<Netlist<'a>>,
+    library: &'a HashMap<String, LibrarySymbol>,
+    options: &'a PlotOptions,
+    /// the colored nets of the page for the legend.
+    nets: Vec<LegendEntry>,
+    legend: bool,
+    /// the ERC markers of the page for the legend.
+    markers: Vec<LegendEntry>,
+    /// the corners taken by the legends.
+    corners: Vec<Corner>,
+    /// the outlines of the plotted items, the legend is placed beside them.
+    outlines: Vec<Array2<f64>>,
+    /// the outline points of the symbols in the view.
+    view: Vec<(f64, f64)>,
+    /// the references and nets of the sub-circuit.
+    selection: Option<Selection>,
+    /// the current element is part of the sub-circuit.
+    selected: bool,
+    /// the pins of the selected symbols on the page.
+    selected_pins: Vec<(f64, f64)>,
+    /// the wires at the selected pins, draws the power nets of the sub-circuit.
+    selected_wires: Vec<Wire>,
+    warnings: Vec<Warning>,
 }
 
 impl<'a, I> Iterator for SchemaPlot<'a, I>
@@ -34,35 +101,109 @@ where
 {
     type Item = Vec<PlotItem>;
     fn next(&mut self) -> Option<Self::Item> {
+        // the border is not dimmed.
+        let border = self.border;
+        let mut items = self.items()?;
+        if (self.legend || !self.markers.is_empty()) && !border {
+            self.outlines.extend(items.iter().filter_map(outline));
+        }
+        if !self.selected {
+            items = self.unselected(items);
+        }
+        match &self.options.highlight {
+            Some(highlight) if !border => {
+                Some(items.into_iter().map(|item| highlight.dim(item)).collect())
+            }
+            _ => Some(items),
+        }
+    }
+}
+
+impl<'a, I> SchemaPlot<'a, I>
+where
+    I: Iterator<Item = &'a SchemaElement>,
+{
+    /// plot the next element.
+    fn items(&mut self) -> Option<Vec<PlotItem>> {
+        let schema = self.schema;
+        let library = self.library;
+        self.selected = true;
         if self.border {
             self.border = false;
-                return Some(draw_border(self.title_block, self.paper_size, self.theme).unwrap());
+            if let Some(worksheet) = &self.options.worksheet {
+                return Some(worksheet.plot(
+                    self.paper_size,
+                    &self.variables,
+                    self.sheet.number,
+                    self.theme,
+                    &mut self.warnings,
+                ));
+            }
+            match draw_border(
+                self.title_block,
+                &self.sheet,
+                &self.variables,
+                self.options.logo.as_deref(),
+                self.paper_size,
+                self.theme,
+            ) {
+                Ok(border) => return Some(border),
+                Err(err) => self.warnings.push(Warning::from_error("border", err)),
+            }
+        }
+        if self.graphics {
+            self.graphics = false;
+            // the drawings are not part of the sub-circuit.
+            let mut items = self.extra.images();
+            items.append(&mut self.graphics());
+            if self.selection.is_some() {
+                items = self.unselected(items);
+            }
+            items.append(&mut self.netclass_flags());
+            items.append(&mut self.markers());
+            let net_tags = self.netlist.is_some()
+                && !self.options.hide.netlist
+                && (self.options.node_names || self.options.operating_point.is_some());
+            if self.options.junctions || self.options.dangling || net_tags {
+                match Connections::new(schema, self.page, library, self.extra) {
+                    Ok(connections) => {
+                        items.append(&mut self.connections(&connections));
+                        if net_tags {
+                            items.append(&mut self.net_tags(&connections));
+                        }
+                    }
+                    Err(err) => self.warnings.push(Warning::from_error("connections", err)),
+                }
+            }
+            return Some(items);
         }
         loop {
-            match self.iter.next() {
+            let element = self.iter.next();
+            if self.is_hidden(element) {
+                continue;
+            }
+            self.selected = self.is_selected(element);
+            match element {
                 Some(SchemaElement::Sheet(sheet)) => {
-                    let prop = sheet
-                        .property
-                        .iter()
-                        .find(|p| p.key == "Sheet name")
-                        .unwrap();
-                    let effects = get_effects!(&prop.effects, &self.theme.effects("text").unwrap());
-                    let stroke = Themer::get(&sheet.stroke, &self.theme.stroke("symbol").unwrap());
-                    return Some(vec![
-                        PlotItem::Text(
+                    let mut items: Vec<PlotItem> = Vec::new();
+                    if let Some(prop) = sheet.property.iter().find(|p| p.key == "Sheet name") {
+                        let effects = get_effects!(&prop.effects, &self.effects("text"));
+                        items.push(PlotItem::Text(
                             10,
                             Text::new(
                                 sheet.at.clone(),
                                 0.0,
-                                prop.value.clone(),
+                                self.variables.resolve(&prop.value),
                                 effects.color,
                                 effects.font_size.0,
                                 effects.font.as_str(),
                                 effects.justify,
                                 false,
                             ),
-                        ),
-                        PlotItem::Rectangle(
+                        ));
+                    }
+                    let stroke = Themer::get(&sheet.stroke, &self.stroke("symbol"));
+                    items.push(PlotItem::Rectangle(
                             1,
                             Rectangle::new(
                                 arr2(&[
@@ -74,14 +215,30 @@ where
                                 stroke.linetype,
                                 Some(sheet.fill),
                             ),
-                        ),
-                    ]);
+                    ));
+                    return Some(items);
                 }
                 Some(SchemaElement::Wire(wire)) => {
-                    let stroke = self.theme.stroke("wire").unwrap();
+                    let mut stroke = Themer::get(&wire.stroke, &self.stroke("wire"));
+                    if wire.stroke.color == (0.0, 0.0, 0.0, 0.0) {
+                        if let Some(color) = self.net_color(wire.pts[[0, 0]], wire.pts[[0, 1]]) {
+                            stroke.color = color;
+                        } else if let Some(color) = self
+                            .netclass(&wire.pts)
+                            .and_then(|netclass| self.options.netclass_colors.get(netclass))
+                        {
+                            stroke.color = *color;
+                        }
+                    }
+                    let mut z = 10;
+                    if let Some(highlight) = self.highlight(wire.pts[[0, 0]], wire.pts[[0, 1]]) {
+                        stroke.color = highlight.color;
+                        stroke.width *= highlight.width;
+                        z = HIGHLIGHT_Z;
+                    }
                     return Some(vec![
                         (PlotItem::Line(
-                            10,
+                            z,
                             Line::new(
                                 wire.pts.clone(),
                                 stroke.width,
@@ -93,7 +250,7 @@ where
                     ]);
                 }
                 Some(SchemaElement::Polyline(line)) => {
-                    let stroke = Themer::get(&line.stroke, &self.theme.stroke("bus").unwrap());
+                    let stroke = Themer::get(&line.stroke, &self.stroke("bus"));
                     return Some(vec![
                         (PlotItem::Line(
                             10,
@@ -108,7 +265,7 @@ where
                     ]);
                 }
                 Some(SchemaElement::Bus(bus)) => {
-                    let stroke = Themer::get(&bus.stroke, &self.theme.stroke("bus").unwrap());
+                    let stroke = Themer::get(&bus.stroke, &self.stroke("bus"));
                     return Some(vec![
                         (PlotItem::Line(
                             10,
@@ -123,14 +280,15 @@ where
                     ]);
                 }
                 Some(SchemaElement::BusEntry(bus)) => {
-                    let stroke = Themer::get(&bus.stroke, &self.theme.stroke("bus").unwrap());
+                    // the bus entries connect wires and are drawn like wires.
+                    let stroke = Themer::get(&bus.stroke, &self.stroke("wire"));
                     return Some(vec![
                         (PlotItem::Line(
                             10,
                             Line::new(
                                 arr2(&[
                                     [bus.at[0], bus.at[1]],
-                                    [bus.at[1] + bus.size[0], bus.at[1] + bus.size[1]],
+                                    [bus.at[0] + bus.size[0], bus.at[1] + bus.size[1]],
                                 ]),
                                 stroke.width,
                                 stroke.linetype.clone(),
@@ -141,7 +299,7 @@ where
                     ]);
                 }
                 Some(SchemaElement::Text(text)) => {
-                    let effects = Themer::get(&text.effects, &self.theme.effects("text").unwrap());
+                    let effects = Themer::get(&text.effects, &self.effects("text"));
                     let pos: Array1<f64> = text.at.clone();
                     let mut angle: f64 = text.angle;
                     if angle >= 180.0 {
@@ -153,7 +311,7 @@ where
                         Text::new(
                             pos,
                             angle,
-                            text.text.clone(),
+                            self.variables.resolve(&text.text),
                             effects.color,
                             effects.font_size.0,
                             effects.font.as_str(),
@@ -163,7 +321,7 @@ where
                     )]);
                 }
                 Some(SchemaElement::NoConnect(no_connect)) => {
-                    let stroke = self.theme.stroke("no_connect").unwrap();
+                    let stroke = self.stroke("no_connect");
                     let pos: Array1<f64> = no_connect.at.clone();
                     let lines1 = arr2(&[[-0.8, 0.8], [0.8, -0.8]]) + &pos;
                     let lines2 = arr2(&[[0.8, 0.8], [-0.8, -0.8]]) + &pos;
@@ -192,12 +350,20 @@ where
                     ]);
                 }
                 Some(SchemaElement::Junction(junction)) => {
-                    let stroke = self.theme.stroke("junction").unwrap();
+                    let mut stroke = self.stroke("junction");
+                    if let Some(color) = self.net_color(junction.at[0], junction.at[1]) {
+                        stroke.color = color;
+                    }
+                    let mut z = 99;
+                    if let Some(highlight) = self.highlight(junction.at[0], junction.at[1]) {
+                        stroke.color = highlight.color;
+                        z = HIGHLIGHT_Z;
+                    }
                     return Some(vec![PlotItem::Circle(
-                        99,
+                        z,
                         Circle::new(
                             junction.at.clone(),
-                            0.35,
+                            JUNCTION_RADIUS,
                             stroke.width,
                             stroke.linetype,
                             stroke.color,
@@ -207,19 +373,20 @@ where
                 }
                 Some(SchemaElement::Label(label)) => {
                     let effects =
-                        Themer::get(&label.effects, &self.theme.effects("label").unwrap());
+                        Themer::get(&label.effects, &self.effects("label"));
                     let pos: Array1<f64> = label.at.clone();
                     let mut angle: f64 = label.angle;
                     if angle >= 180.0 {
                         angle -= 180.0;
                     }
+                    let (text, color, z) = self.label(&label.text, &label.at);
                     return Some(vec![PlotItem::Text(
-                        10,
+                        z,
                         Text::new(
                             pos,
                             angle,
-                            label.text.clone(),
-                            effects.color,
+                            text,
+                            color.unwrap_or(effects.color),
                             effects.font_size.0,
                             effects.font.as_str(),
                             effects.justify,
@@ -228,19 +395,20 @@ where
                     )]);
                 }
                 Some(SchemaElement::GlobalLabel(label)) => {
-                    let effects = self.theme.effects("global_label").unwrap();
+                    let effects = self.effects("global_label");
                     let pos: Array1<f64> = label.at.clone();
                     let mut angle: f64 = label.angle;
                     if angle > 180.0 {
                         angle -= 180.0;
                     }
+                    let (text, color, z) = self.label(&label.text, &label.at);
                     return Some(vec![PlotItem::Text(
-                        10,
+                        z,
                         Text::new(
                             pos,
                             angle,
-                            label.text.clone(),
-                            effects.color,
+                            text,
+                            color.unwrap_or(effects.color),
                             effects.font_size.0,
                             effects.font.as_str(),
                             effects.justify,
@@ -249,19 +417,20 @@ where
                     )]);
                 }
                 Some(SchemaElement::HierarchicalLabel(label)) => {
-                    let effects = self.theme.effects("label").unwrap();
+                    let effects = self.effects("label");
                     let pos: Array1<f64> = label.at.clone();
                     let mut angle: f64 = label.angle;
                     if angle >= 180.0 {
                         angle -= 180.0;
                     }
+                    let (text, color, z) = self.label(&label.text, &label.at);
                     return Some(vec![PlotItem::Text(
-                        10,
+                        z,
                         Text::new(
                             pos,
                             angle,
-                            label.text.clone(),
-                            effects.color,
+                            text,
+                            color.unwrap_or(effects.color),
                             effects.font_size.0,
                             effects.font.as_str(),
                             effects.justify,
@@ -271,12 +440,13 @@ where
                 }
                 Some(SchemaElement::Symbol(symbol)) => {
                     if symbol.on_schema {
+                        let style = Style::from(&self.options.styles, symbol, self.is_dnp(symbol));
                         let mut items: Vec<PlotItem> = Vec::new();
                         for property in &symbol.property {
-                            let mut effects = get_effects!(
+                            let mut effects = style.effects(get_effects!(
                                 &property.effects,
-                                &self.theme.effects("property").unwrap()
-                            );
+                                &self.effects("property")
+                            ));
                             let mut justify: Vec<String> = Vec::new();
                             for j in effects.justify {
                                 if property.angle + symbol.angle >= 180.0
@@ -299,24 +469,31 @@ where
                             } else {
                                 (symbol.angle - property.angle).abs()
                             };
-                            if !effects.hide {
+                            if !effects.hide && !self.options.hide.field(&property.key) {
                                 items.push(text!(
                                     property.at.clone(),
                                     prop_angle.abs(),
-                                    property.value.clone(),
+                                    self.variables.resolve_symbol(&property.value, symbol),
                                     effects
                                 ));
                             }
                         }
-                        if let Some(lib) = self.schema.get_library(&symbol.lib_id) {
-                            for _unit in &self.schema.get_library(&symbol.lib_id).unwrap().symbols {
+                        if let Some(mut current) = self.current(symbol) {
+                            items.append(&mut current);
+                        }
+                        if let Some(lib) = schema
+                            .get_library(&symbol.lib_id)
+                            .or_else(|| library.get(&symbol.lib_id))
+                        {
+                            for _unit in &lib.symbols {
                                 if _unit.unit == 0 || _unit.unit == symbol.unit {
                                     for graph in &_unit.graph {
                                         match graph {
                                             Graph::Polyline(polyline) => {
-                                                let stroke = Themer::get(
+                                                let stroke = Themer::styled(
                                                     &polyline.stroke,
-                                                    &self.theme.stroke("symbol").unwrap(),
+                                                    &self.stroke("symbol"),
+                                                    &style,
                                                 );
                                                 // let z: usize = if let None = fill_color { 10 } else { 1 };
                                                 items.push(PlotItem::Polyline(
@@ -326,14 +503,17 @@ where
                                                         stroke.color,
                                                         stroke.width,
                                                         stroke.linetype,
-                                                        self.theme.color(&polyline.fill_type),
+                                                        style.fill_color(
+                                                            self.theme.color(&polyline.fill_type),
+                                                        ),
                                                     ),
                                                 ));
                                             }
                                             Graph::Rectangle(rectangle) => {
-                                                let stroke = Themer::get(
+                                                let stroke = Themer::styled(
                                                     &rectangle.stroke,
-                                                    &self.theme.stroke("symbol").unwrap(),
+                                                    &self.stroke("symbol"),
+                                                    &style,
                                                 );
                                                 let start = &rectangle.start;
                                                 let end = &rectangle.end;
@@ -347,14 +527,17 @@ where
                                                         stroke.color,
                                                         stroke.width,
                                                         stroke.linetype,
-                                                        self.theme.color(&rectangle.fill_type),
+                                                        style.fill_color(
+                                                            self.theme.color(&rectangle.fill_type),
+                                                        ),
                                                     ),
                                                 ));
                                             }
                                             Graph::Circle(circle) => {
-                                                let stroke = Themer::get(
+                                                let stroke = Themer::styled(
                                                     &circle.stroke,
-                                                    &self.theme.stroke("symbol").unwrap(),
+                                                    &self.stroke("symbol"),
+                                                    &style,
                                                 );
                                                 // let z: usize = if let None = fill_color { 10 } else { 1 };
                                                 items.push(PlotItem::Circle(
@@ -365,39 +548,46 @@ where
                                                         stroke.width,
                                                         stroke.linetype,
                                                         stroke.color,
-                                                        self.theme.color(&circle.fill_type),
+                                                        style.fill_color(
+                                                            self.theme.color(&circle.fill_type),
+                                                        ),
                                                     ),
                                                 ));
                                             }
                                             Graph::Arc(arc) => {
-                                                let stroke = Themer::get(
+                                                let stroke = Themer::styled(
                                                     &arc.stroke,
-                                                    &self.theme.stroke("symbol").unwrap(),
+                                                    &self.stroke("symbol"),
+                                                    &style,
                                                 );
                                                 // let z: usize = if let None = _fill_color { 10 } else { 1 };
                                                 items.push(PlotItem::Arc(
                                                     1,
                                                     Arc::new(
                                                         Shape::transform(symbol, &arc.start),
-                                                        arc.mid.clone(),
-                                                        arc.end.clone(),
+                                                        Shape::transform(symbol, &arc.mid),
+                                                        Shape::transform(symbol, &arc.end),
                                                         stroke.width,
                                                         stroke.linetype,
                                                         stroke.color,
-                                                        self.theme.color(&arc.fill_type),
+                                                        style.fill_color(
+                                                            self.theme.color(&arc.fill_type),
+                                                        ),
                                                     ),
                                                 ));
                                             }
                                             Graph::Text(text) => {
-                                                let effects = Themer::get(
+                                                let effects = Themer::styled(
                                                     &text.effects,
-                                                    &self.theme.effects("symbol").unwrap(),
+                                                    &self.effects("property"),
+                                                    &style,
                                                 );
                                                 // let z: usize = if let None = _fill_color { 10 } else { 1 };
                                                 items.push(text!(
                                                     Shape::transform(symbol, &text.at),
                                                     text.angle,
-                                                    text.text.clone(),
+                                                    self.variables
+                                                        .resolve_symbol(&text.text, symbol),
                                                     effects
                                                 ));
                                             }
@@ -408,7 +598,20 @@ where
                                         /* if graph.has("hide") {
                                             break;
                                         } */
-                                        let stroke = self.theme.stroke("pin").unwrap();
+                                        let mut stroke = style.stroke(self.stroke("pin"));
+                                        let pin_pos = Shape::transform(symbol, &pin.at);
+                                        if let Some(color) = self.net_color(pin_pos[0], pin_pos[1])
+                                        {
+                                            stroke.color = color;
+                                        }
+                                        let mut z = 10;
+                                        if let Some(highlight) =
+                                            self.highlight(pin_pos[0], pin_pos[1])
+                                        {
+                                            stroke.color = highlight.color;
+                                            stroke.width *= highlight.width;
+                                            z = HIGHLIGHT_Z;
+                                        }
                                         let pin_line: Array2<f64> = arr2(&[
                                             [pin.at[0], pin.at[1]],
                                             [
@@ -420,7 +623,7 @@ where
                                         ]);
 
                                         items.push(PlotItem::Line(
-                                            10,
+                                            z,
                                             Line::new(
                                                 Shape::transform(symbol, &pin_line),
                                                 stroke.width,
@@ -430,7 +633,10 @@ where
                                             ),
                                         ));
 
-                                        if !lib.power && lib.pin_numbers_show {
+                                        if !lib.power
+                                            && lib.pin_numbers_show
+                                            && !self.options.hide.pin_numbers
+                                        {
                                             let orientation = pin_position(symbol, pin);
                                             let pos = if orientation == vec![1, 0, 0, 0] {
                                                 Shape::transform(symbol, &pin.at) + arr1(&[
@@ -447,14 +653,14 @@ where
                                                     pin.angle.to_radians().cos() * pin.length / 2.0,
                                                     -1.0
                                                 ])
-                                            } else if orientation == vec![0, 0, 0, 1] {
+                                            } else {
                                                 Shape::transform(symbol, &pin.at) + arr1(&[
                                                     1.0,
                                                     -pin.angle.to_radians().cos() * pin.length / 2.0,
                                                 ])
-                                            } else { panic!("unknown pin position: {:?}", orientation)};
+                                            };
 
-                                            let effects = self.theme.effects("pin_number").unwrap();
+                                            let effects = style.effects(self.effects("pin_number"));
                                             items.push(text!(
                                                 pos,
                                                 0.0,
@@ -462,7 +668,11 @@ where
                                                 effects
                                             ));
                                         }
-                                        if !lib.power && pin.name.0 != "~" && lib.pin_names_show {
+                                        if !lib.power
+                                            && pin.name.0 != "~"
+                                            && lib.pin_names_show
+                                            && !self.options.hide.pin_names
+                                        {
                                             let name_pos = arr1(&[
                                                 pin.at[0]
                                                     + pin.angle.to_radians().cos()
@@ -471,7 +681,7 @@ where
                                                     + pin.angle.to_radians().sin()
                                                         * (pin.length + lib.pin_names_offset * 8.0),
                                             ]);
-                                            let effects = self.theme.effects("pin_name").unwrap();
+                                            let effects = style.effects(self.effects("pin_name"));
                                             items.push(PlotItem::Text(
                                                 99,
                                                 Text::new(
@@ -486,46 +696,12 @@ where
                                                 ),
                                             ));
                                         }
-                                        // draw the netlist name
-                                        if !lib.power {
-                                            if let Some(netlist) = self.netlist {
-                                                let orientation = pin_position(symbol, pin);
-                                                let pos = if orientation == vec![1, 0, 0, 0] {
-                                                    Shape::transform(symbol, &pin.at) + arr1(&[
-                                                        pin.angle.to_radians().cos() * pin.length / 2.0,
-                                                        1.0,
-                                                    ])
-                                                } else if orientation == vec![0, 1, 0, 0] {
-                                                    Shape::transform(symbol, &pin.at) + arr1(&[
-                                                        -1.0,
-                                                        pin.angle.to_radians().cos() * pin.length / 2.0,
-                                                    ])
-                                                } else if orientation == vec![0, 0, 1, 0] {
-                                                    Shape::transform(symbol, &pin.at) + arr1(&[
-                                                        pin.angle.to_radians().cos() * pin.length / 2.0,
-                                                        1.0
-                                                    ])
-                                                } else if orientation == vec![0, 0, 0, 1] {
-                                                    Shape::transform(symbol, &pin.at) + arr1(&[
-                                                        -1.0,
-                                                        -pin.angle.to_radians().cos() * pin.length / 2.0,
-                                                    ])
-                                                } else { panic!("unknown pin position: {:?}", orientation)};
-
-                                                let effects = self.theme.effects("pin_number").unwrap();
-                                                let pin_pos = Shape::transform(symbol, &pin.at);
-                                                items.push(text!(
-                                                    pos,
-                                                    0.0,
-                                                    netlist.node_name(&Point::new(pin_pos[0], pin_pos[1])).unwrap_or_else(|| String::from("NaN")),
-                                                    effects
-                                                ));
-                                            }
-                                        }
                                     }
                                 }
                             }
                         } else {
+                            self.warnings
+                                .push(Warning::MissingLibrarySymbol(symbol.lib_id.clone()));
                             let pts = arr2(&[[0.0, 0.0], [10.0, 10.0]]);
                             items.push(PlotItem::Rectangle(
                                 10,
@@ -538,11 +714,24 @@ where
                                 ),
                             ));
                         }
+                        self.view_points(symbol, &items);
                         return Some(items);
                     }
                 }
                 None => {
-                    return None;
+                    // the legends are drawn after all nets and outlines are collected.
+                    let mut items: Vec<PlotItem> = Vec::new();
+                    if self.legend {
+                        self.legend = false;
+                        let mut nets = std::mem::take(&mut self.nets);
+                        nets.sort_by(|a, b| a.0.cmp(&b.0));
+                        items.append(&mut self.legend_items(&nets));
+                    }
+                    if !self.markers.is_empty() {
+                        let markers = std::mem::take(&mut self.markers);
+                        items.append(&mut self.legend_items(&markers));
+                    }
+                    return if items.is_empty() { None } else { Some(items) };
                 }
             }
         }
@@ -566,9 +755,9 @@ where
 ///   1
 fn pin_position(symbol: &Symbol, pin: &Pin) -> Vec<usize> {
     let mut position: Vec<usize> = vec![0; 4];
-    let symbol_shift: usize = (symbol.angle / 90.0).round() as usize;
+    let symbol_shift: usize = ((symbol.angle / 90.0).round() as i32).rem_euclid(4) as usize;
 
-    let lib_pos: usize = (pin.angle / 90.0).round() as usize;
+    let lib_pos: usize = ((pin.angle / 90.0).round() as i32).rem_euclid(4) as usize;
     position[lib_pos] += 1;
 
     position.rotate_right(symbol_shift);
@@ -582,23 +771,802 @@ fn pin_position(symbol: &Symbol, pin: &Pin) -> Vec<usize> {
     position
 }
 
+/// The page of the schema and the plot settings.
+pub struct PlotContext<'a> {
+    pub schema: &'a Schema,
+    pub title_block: &'a Option<TitleBlock>,
+    pub paper_size: (f64, f64),
+    /// the index of the page in the schema.
+    pub page: usize,
+    pub sheet: SheetInfo,
+    pub variables: Variables,
+    pub extra: &'a Extra,
+    pub theme: &'a Theme,
+    pub netlist: &'a Option<Netlist<'a>>,
+    pub library: &'a HashMap<String, LibrarySymbol>,
+    pub options: &'a PlotOptions,
+}
+
 impl<'a, I> SchemaPlot<'a, I> {
-    pub fn new(iter: I, schema: &'a Schema, title_block: &'a Option<TitleBlock>, paper_size: (f64, f64), theme: &'a Theme, border: bool, netlist: &'a Option<Netlist<'a>>) -> Self {
+    pub fn new(iter: I, context: PlotContext<'a>) -> Self {
+        let PlotContext {
+            schema,
+            title_block,
+            paper_size,
+            page,
+            sheet,
+            variables,
+            extra,
+            theme,
+            netlist,
+            library,
+            options,
+        } = context;
         Self {
             iter,
             theme,
-            border,
+            border: options.border,
             schema,
             title_block,
             paper_size,
+            page,
+            sheet,
+            variables,
+            extra,
+            graphics: true,
             netlist,
+            library,
+            options,
+            nets: Vec::new(),
+            legend: options.net_colors && options.net_legend,
+            markers: Vec::new(),
+            corners: Vec::new(),
+            outlines: Vec::new(),
+            view: Vec::new(),
+            selection: None,
+            selected: true,
+            selected_pins: Vec::new(),
+            selected_wires: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+    /// plot only the sub-circuit, the other elements are removed or ghosted.
+    pub fn select(&mut self, selection: Option<Selection>) {
+        if let Some(selection) = &selection {
+            let wires =
+                selected_pins(self.schema, self.page, self.library, selection).and_then(|pins| {
+                    let connections =
+                        Connections::new(self.schema, self.page, self.library, self.extra)?;
+                    Ok((connections.touching(&pins), pins))
+                });
+            match wires {
+                Ok((wires, pins)) => {
+                    self.selected_wires = wires;
+                    self.selected_pins = pins;
+                }
+                Err(err) => self.warnings.push(Warning::from_error("sub-circuit", err)),
+            }
+        }
+        self.selection = selection;
+    }
+    /// get the first corner where the legend does not cover the plotted items.
+    ///
+    /// The corners of the other legends are not used.
+    fn legend_corner(&self, entries: &[LegendEntry]) -> Corner {
+        let corners: Vec<Corner> = [Corner::TopRight, Corner::TopLeft, Corner::BottomLeft]
+            .into_iter()
+            .filter(|corner| !self.corners.contains(corner))
+            .collect();
+        corners
+            .iter()
+            .copied()
+            .find(
+                |corner| match legend_bounds(entries, *corner, self.paper_size, self.theme) {
+                    Ok(((x0, y0), (x1, y1))) => !self.outlines.iter().any(|outline| {
+                        let (xs, ys) = (outline.column(0), outline.column(1));
+                        xs.iter().any(|x| *x >= x0)
+                            && xs.iter().any(|x| *x <= x1)
+                            && ys.iter().any(|y| *y >= y0)
+                            && ys.iter().any(|y| *y <= y1)
+                    }),
+                    Err(_) => false,
+                },
+            )
+            .or_else(|| corners.first().copied())
+            .unwrap_or(Corner::TopRight)
+    }
+    /// draw the legend in a free corner, the following legends are placed beside it.
+    fn legend_items(&mut self, entries: &[LegendEntry]) -> Vec<PlotItem> {
+        let corner = self.legend_corner(entries);
+        self.corners.push(corner);
+        match draw_legend(entries, corner, self.paper_size, self.theme) {
+            Ok(legend) => {
+                if let Ok(((x0, y0), (x1, y1))) =
+                    legend_bounds(entries, corner, self.paper_size, self.theme)
+                {
+                    self.outlines.push(arr2(&[[x0, y0], [x1, y1]]));
+                }
+                legend
+            }
+            Err(err) => {
+                self.warnings.push(Warning::from_error("legend", err));
+                Vec::new()
+            }
+        }
+    }
+    /// remove or ghost the items which are not part of the sub-circuit.
+    fn unselected(&self, items: Vec<PlotItem>) -> Vec<PlotItem> {
+        match self.options.subcircuit.as_ref().and_then(|s| s.ghost) {
+            Some(alpha) => items.into_iter().map(|item| fade(item, alpha)).collect(),
+            None => Vec::new(),
+        }
+    }
+    /// test if the point is on a net of the sub-circuit or at a selected pin.
+    fn on_selection(&self, pt: (f64, f64)) -> bool {
+        let (selection, netlist) = match (&self.selection, self.netlist) {
+            (Some(selection), Some(netlist)) => (selection, netlist),
+            _ => return true,
+        };
+        netlist
+            .node_name(&Point::new(pt.0, pt.1))
+            .map(|name| selection.nets.contains(&name))
+            .unwrap_or(false)
+            || self.selected_pins.iter().any(|pin| same(*pin, pt))
+            || self
+                .selected_wires
+                .iter()
+                .any(|(start, end)| on_wire(*start, *end, pt))
+    }
+    /// remove or ghost the items from the start index when the point is not selected.
+    fn select_tail(&self, items: &mut Vec<PlotItem>, start: usize, pt: (f64, f64)) {
+        if !self.on_selection(pt) {
+            let tail = items.split_off(start);
+            items.append(&mut self.unselected(tail));
+        }
+    }
+    /// test if the element is part of the sub-circuit.
+    fn is_selected(&self, element: Option<&SchemaElement>) -> bool {
+        let selection = match (&self.selection, self.netlist) {
+            (Some(selection), Some(_)) => selection,
+            _ => return true,
+        };
+        let on_net = |x: f64, y: f64| self.on_selection((x, y));
+        match element {
+            Some(SchemaElement::Symbol(symbol)) => {
+                let reference = symbol
+                    .property
+                    .iter()
+                    .find(|p| p.key == "Reference")
+                    .map(|p| p.value.as_str())
+                    .unwrap_or_default();
+                // power symbols are drawn on the nets of the sub-circuit.
+                selection.references.contains(reference)
+                    || (reference.starts_with('#') && on_net(symbol.at[0], symbol.at[1]))
+            }
+            Some(SchemaElement::Wire(wire)) => {
+                wire.pts.rows().into_iter().any(|pt| on_net(pt[0], pt[1]))
+            }
+            // the buses are also selected at the entries of the selected wires.
+            Some(SchemaElement::Bus(bus)) => {
+                let on_bus = |pt: (f64, f64)| on_segment(&bus.pts, pt);
+                bus.pts.rows().into_iter().any(|pt| on_net(pt[0], pt[1]))
+                    || self
+                        .schema
+                        .iter(self.page)
+                        .into_iter()
+                        .flatten()
+                        .any(|element| {
+                            let SchemaElement::BusEntry(entry) = element else {
+                                return false;
+                            };
+                            let (start, end) = bus_entry_ends(entry);
+                            (on_net(start.0, start.1) && on_bus(end))
+                                || (on_net(end.0, end.1) && on_bus(start))
+                        })
+            }
+            Some(SchemaElement::BusEntry(entry)) => {
+                let (start, end) = bus_entry_ends(entry);
+                on_net(start.0, start.1) || on_net(end.0, end.1)
+            }
+            Some(SchemaElement::Sheet(sheet)) => {
+                sheet.pin.iter().any(|pin| on_net(pin.at[0], pin.at[1]))
+            }
+            Some(SchemaElement::Text(text)) => on_net(text.at[0], text.at[1]),
+            Some(SchemaElement::Junction(junction)) => on_net(junction.at[0], junction.at[1]),
+            Some(SchemaElement::NoConnect(no_connect)) => {
+                on_net(no_connect.at[0], no_connect.at[1])
+            }
+            Some(SchemaElement::Label(label)) => on_net(label.at[0], label.at[1]),
+            Some(SchemaElement::GlobalLabel(label)) => on_net(label.at[0], label.at[1]),
+            Some(SchemaElement::HierarchicalLabel(label)) => on_net(label.at[0], label.at[1]),
+            None => true,
+            _ => false,
+        }
+    }
+    /// test if the category of the element is hidden.
+    fn is_hidden(&self, element: Option<&SchemaElement>) -> bool {
+        let hide = &self.options.hide;
+        match element {
+            Some(SchemaElement::Text(_)) => hide.notes,
+            Some(SchemaElement::NoConnect(_)) => hide.no_connects,
+            Some(SchemaElement::Symbol(symbol)) => {
+                let power = hide.power_symbols
+                    && self
+                        .schema
+                        .get_library(&symbol.lib_id)
+                        .or_else(|| self.library.get(&symbol.lib_id))
+                        .map(|lib| lib.power)
+                        .unwrap_or(false);
+                power || Style::from(&self.options.styles, symbol, self.is_dnp(symbol)).hide
+            }
+            _ => false,
+        }
+    }
+    /// test if the symbol is marked as do not populate in the schema file.
+    fn is_dnp(&self, symbol: &Symbol) -> bool {
+        self.extra.dnp.contains(&symbol.uuid)
+    }
+    /// get the outline points of the symbols in the view.
+    pub fn view(&self) -> &[(f64, f64)] {
+        &self.view
+    }
+    /// collect the outline of the symbol when the reference is in the view.
+    fn view_points(&mut self, symbol: &Symbol, items: &[PlotItem]) {
+        let options = self.options;
+        let view = match &options.view {
+            Some(view) if view.has_references() => view,
+            _ => return,
+        };
+        let reference = symbol.property.iter().find(|p| p.key == "Reference");
+        if let Some(reference) = reference {
+            if view.matches(&self.variables.resolve_symbol(&reference.value, symbol)) {
+                for item in items {
+                    if let Some(outline) = outline(item) {
+                        for row in outline.rows() {
+                            self.view.push((row[0], row[1]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    /// take the warnings collected while plotting.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+    fn stroke(&mut self, name: &str) -> Stroke {
+        self.theme.stroke_or_default(name, &mut self.warnings)
+    }
+    fn effects(&mut self, name: &str) -> Effects {
+        self.theme.effects_or_default(name, &mut self.warnings)
+    }
+    fn fill(&self, fill: &Fill) -> Option<(f64, f64, f64, f64)> {
+        if fill.fill_type == "color" {
+            fill.color
+        } else {
+            self.theme.color(&fill.fill_type)
         }
     }
+    /// plot the free standing graphic items.
+    fn graphics(&mut self) -> Vec<PlotItem> {
+        let extra = self.extra;
+        let mut items: Vec<PlotItem> = Vec::new();
+        for graphic in &extra.graphics {
+            match graphic {
+                Graphic::Rectangle {
+                    start,
+                    end,
+                    stroke,
+                    fill,
+                } => {
+                    let stroke = Themer::get(stroke, &self.stroke("notes"));
+                    items.push(PlotItem::Rectangle(
+                        1,
+                        Rectangle::new(
+                            arr2(&[[start.0, start.1], [end.0, end.1]]),
+                            stroke.color,
+                            stroke.width,
+                            stroke.linetype,
+                            self.fill(fill),
+                        ),
+                    ));
+                }
+                Graphic::Circle {
+                    center,
+                    radius,
+                    stroke,
+                    fill,
+                } => {
+                    let stroke = Themer::get(stroke, &self.stroke("notes"));
+                    items.push(PlotItem::Circle(
+                        1,
+                        Circle::new(
+                            arr1(&[center.0, center.1]),
+                            *radius,
+                            stroke.width,
+                            stroke.linetype,
+                            stroke.color,
+                            self.fill(fill),
+                        ),
+                    ));
+                }
+                Graphic::Arc {
+                    start,
+                    mid,
+                    end,
+                    stroke,
+                    fill,
+                } => {
+                    let stroke = Themer::get(stroke, &self.stroke("notes"));
+                    items.push(PlotItem::Arc(
+                        1,
+                        Arc::new(
+                            arr1(&[start.0, start.1]),
+                            arr1(&[mid.0, mid.1]),
+                            arr1(&[end.0, end.1]),
+                            stroke.width,
+                            stroke.linetype,
+                            stroke.color,
+                            self.fill(fill),
+                        ),
+                    ));
+                }
+                Graphic::Bezier { pts, stroke, fill } => {
+                    let stroke = Themer::get(stroke, &self.stroke("notes"));
+                    items.push(PlotItem::Bezier(
+                        1,
+                        Bezier::new(
+                            points(pts),
+                            stroke.color,
+                            stroke.width,
+                            stroke.linetype,
+                            self.fill(fill),
+                        ),
+                    ));
+                }
+                Graphic::TextBox { .. } if self.options.hide.notes => {}
+                Graphic::TextBox {
+                    text,
+                    at,
+                    angle,
+                    size,
+                    margins,
+                    stroke,
+                    fill,
+                    effects: text_effects,
+                } => {
+                    let theme_effects = self.effects("text_box");
+                    let mut effects = Themer::get(text_effects, &theme_effects);
+                    if text_effects.color == (0.0, 0.0, 0.0, 0.0) {
+                        effects.color = theme_effects.color;
+                    }
+                    let stroke = Themer::get(stroke, &self.stroke("notes"));
+                    let (x0, y0) = (at.0.min(at.0 + size.0), at.1.min(at.1 + size.1));
+                    let (x1, y1) = (at.0.max(at.0 + size.0), at.1.max(at.1 + size.1));
+                    // a negative width hides the border.
+                    if stroke.width >= 0.0 || fill.fill_type != "none" {
+                        items.push(PlotItem::Rectangle(
+                            1,
+                            Rectangle::new(
+                                arr2(&[[x0, y0], [x1, y1]]),
+                                if stroke.width >= 0.0 {
+                                    stroke.color
+                                } else {
+                                    (0.0, 0.0, 0.0, 0.0)
+                                },
+                                stroke.width.max(0.0),
+                                stroke.linetype,
+                                self.fill(fill),
+                            ),
+                        ));
+                    }
+                    // the default margin of older files depends on the text size.
+                    let margin = effects.font_size.1 * 0.75;
+                    let (left, top, right, bottom) =
+                        margins.unwrap_or((margin, margin, margin, margin));
+                    // place the text between the margins, the start and end of the text axis.
+                    let align = |start: f64, end: f64, first: &str, second: &str| {
+                        if effects.justify.iter().any(|j| j == first) {
+                            start
+                        } else if effects.justify.iter().any(|j| j == second) {
+                            end
+                        } else {
+                            (start + end) / 2.0
+                        }
+                    };
+                    let pos = if *angle == 90.0 || *angle == 270.0 {
+                        // the text runs upwards, left is at the bottom of the box.
+                        arr1(&[
+                            align(x0 + left, x1 - right, "top", "bottom"),
+                            align(y1 - bottom, y0 + top, "left", "right"),
+                        ])
+                    } else {
+                        arr1(&[
+                            align(x0 + left, x1 - right, "left", "right"),
+                            align(y0 + top, y1 - bottom, "top", "bottom"),
+                        ])
+                    };
+                    items.push(PlotItem::Text(
+                        10,
+                        Text::new(
+                            pos,
+                            *angle,
+                            self.variables.resolve(text),
+                            effects.color,
+                            effects.font_size.0,
+                            effects.font.as_str(),
+                            effects.justify,
+                            false,
+                        ),
+                    ));
+                }
+            }
+        }
+        items
+    }
+    /// plot the ERC markers of the sheet, the numbered legend is drawn at the end.
+    ///
+    /// The markers are numbered over all sheets.
+    fn markers(&mut self) -> Vec<PlotItem> {
+        let mut items: Vec<PlotItem> = Vec::new();
+        let effects = self.effects("border_effects");
+        for (i, marker) in self.options.markers.iter().enumerate() {
+            if marker.sheet != self.sheet.path {
+                continue;
+            }
+            let start = items.len();
+            let color = marker.severity.color();
+            let pos = arr1(&[marker.pos.0, marker.pos.1]);
+            items.push(PlotItem::Polyline(
+                99,
+                Polyline::new(
+                    arr2(&MARKER_SHAPE) * MARKER_SCALE + &pos,
+                    color,
+                    0.1,
+                    String::from("default"),
+                    Some(color),
+                ),
+            ));
+            items.push(text!(
+                &pos + 14.0 * MARKER_SCALE,
+                0.0,
+                (i + 1).to_string(),
+                effects
+            ));
+            self.select_tail(&mut items, start, marker.pos);
+            self.markers.push((
+                format!("{}: {} ({:?})", i + 1, marker.message, marker.severity),
+                color,
+            ));
+        }
+        items
+    }
+    /// plot the missing junctions and the markers for the dangling ends.
+    fn connections(&mut self, connections: &Connections) -> Vec<PlotItem> {
+        let mut items: Vec<PlotItem> = Vec::new();
+        if self.options.junctions {
+            let stroke = self.stroke("junction");
+            for pt in connections.missing_junctions() {
+                let start = items.len();
+                items.push(PlotItem::Circle(
+                    99,
+                    Circle::new(
+                        arr1(&[pt.0, pt.1]),
+                        JUNCTION_RADIUS,
+                        stroke.width,
+                        stroke.linetype.clone(),
+                        stroke.color,
+                        Some(stroke.color),
+                    ),
+                ));
+                self.select_tail(&mut items, start, pt);
+            }
+        }
+        if self.options.dangling {
+            let wire = self.stroke("wire");
+            let pin = self.stroke("pin");
+            for (pts, stroke) in [
+                (connections.dangling_wires(), wire),
+                (connections.dangling_pins(), pin),
+            ] {
+                for pt in pts {
+                    let start = items.len();
+                    items.push(PlotItem::Circle(
+                        99,
+                        Circle::new(
+                            arr1(&[pt.0, pt.1]),
+                            DANGLING_RADIUS,
+                            stroke.width / 2.0,
+                            stroke.linetype.clone(),
+                            stroke.color,
+                            None,
+                        ),
+                    ));
+                    self.select_tail(&mut items, start, pt);
+                }
+            }
+        }
+        items
+    }
+    /// plot one tag per net segment with the node name and the operating point voltage.
+    ///
+    /// The tag is placed on a wire of the segment, away from the symbols, texts
+    /// and the other tags. Unresolved nodes get the placeholder or are skipped.
+    /// Nets without wires, like pins connected directly to pins, get no tag.
+    fn net_tags(&mut self, connections: &Connections) -> Vec<PlotItem> {
+        let mut items: Vec<PlotItem> = Vec::new();
+        let netlist = match self.netlist {
+            Some(netlist) => netlist,
+            None => return items,
+        };
+        let options = self.options;
+        let mut obstacles = connections.obstacles();
+        for segment in connections.segments() {
+            let start = segment[0].0;
+            let node = netlist.node_name(&Point::new(start.0, start.1));
+            let voltage = match (&options.operating_point, &node) {
+                (Some(op), Some(node)) => op
+                    .voltage(node)
+                    .map(|v| format_si(v, "V", options.op_precision)),
+                _ => None,
+            };
+            let name = if options.node_names {
+                node.or_else(|| options.node_placeholder.clone())
+            } else {
+                None
+            };
+            let effects = self.effects(if voltage.is_some() {
+                "operating_point"
+            } else {
+                "node_name"
+            });
+            let name = match (name, voltage) {
+                (Some(name), Some(voltage)) => format!("{} {}", name, voltage),
+                (Some(text), None) | (None, Some(text)) => text,
+                (None, None) => continue,
+            };
+            let size = (text_width(&name, effects.font_size.0), effects.font_size.1);
+            if let Some((min, max)) = place_tag(&segment, size, &obstacles) {
+                obstacles.push((min, max));
+                let first = items.len();
+                items.push(PlotItem::Rectangle(
+                    99,
+                    Rectangle::new(
+                        arr2(&[[min.0, min.1], [max.0, max.1]]),
+                        (1.0, 1.0, 1.0, 0.8),
+                        0.0,
+                        String::from("default"),
+                        Some((1.0, 1.0, 1.0, 0.8)),
+                    ),
+                ));
+                items.push(PlotItem::Text(
+                    99,
+                    Text::new(
+                        arr1(&[min.0, max.1]),
+                        0.0,
+                        name,
+                        effects.color,
+                        effects.font_size.0,
+                        &effects.font,
+                        vec![String::from("left"), String::from("bottom")],
+                        false,
+                    ),
+                ));
+                self.select_tail(&mut items, first, start);
+            }
+        }
+        items
+    }
+    /// plot the operating point current of the symbol below the value field.
+    fn current(&mut self, symbol: &Symbol) -> Option<Vec<PlotItem>> {
+        if self.options.hide.netlist {
+            return None;
+        }
+        let op = self.options.operating_point.as_ref()?;
+        let reference = symbol.property.iter().find(|p| p.key == "Reference")?;
+        let current = op.current(&self.variables.resolve_symbol(&reference.value, symbol))?;
+        let mut effects = self.effects("operating_point");
+        let pos = match symbol.property.iter().find(|p| p.key == "Value") {
+            Some(value) => {
+                effects.justify = get_effects!(&value.effects, &effects).justify;
+                &value.at + arr1(&[0.0, effects.font_size.1 * 1.5])
+            }
+            None => symbol.at.clone(),
+        };
+        Some(vec![text!(
+            pos,
+            0.0,
+            format_si(current, "A", self.options.op_precision),
+            effects
+        )])
+    }
+    /// get the color of the net at the position when the nets are colored.
+    fn net_color(&mut self, x: f64, y: f64) -> Option<(f64, f64, f64, f64)> {
+        if !self.options.net_colors {
+            return None;
+        }
+        let name = self.netlist.as_ref()?.node_name(&Point::new(x, y))?;
+        let color = net_color(&name, &self.options.net_color_map);
+        if !self.nets.iter().any(|(n, _)| *n == name) {
+            self.nets.push((name, color));
+        }
+        Some(color)
+    }
+    /// get the highlight when the net at the position is highlighted.
+    fn highlight(&self, x: f64, y: f64) -> Option<&'a Highlight> {
+        let options: &'a PlotOptions = self.options;
+        let highlight = options.highlight.as_ref()?;
+        let name = self.netlist.as_ref()?.node_name(&Point::new(x, y))?;
+        if highlight.matches(&name) {
+            Some(highlight)
+        } else {
+            None
+        }
+    }
+    /// get the label text, the color and the z order of the label.
+    ///
+    /// The color is set when the label names a bus, the nets are colored or highlighted.
+    /// The bus aliases are resolved for the netlist overlay.
+    fn label(&mut self, text: &str, at: &Array1<f64>) -> Label {
+        let text = self.variables.resolve(text);
+        if !bus::is_bus(&text) {
+            if let Some(highlight) = self.highlight(at[0], at[1]) {
+                return (text, Some(highlight.color), HIGHLIGHT_Z);
+            }
+            let color = self.net_color(at[0], at[1]);
+            return (text, color, 10);
+        }
+        let text = if self.netlist.is_some() {
+            bus::resolve_aliases(&text, &self.extra.bus_aliases)
+        } else {
+            text
+        };
+        (text, Some(self.stroke("bus").color), 10)
+    }
+    /// get the netclass of the wire from the netclass flags.
+    ///
+    /// With a netlist the flag can be anywhere on the net, otherwise it must be on the wire.
+    fn netclass(&self, pts: &Array2<f64>) -> Option<&'a str> {
+        let extra: &'a Extra = self.extra;
+        extra
+            .netclass_flags
+            .iter()
+            .find(|flag| {
+                if let Some(netlist) = self.netlist {
+                    let node = netlist.node_name(&Point::new(flag.at.0, flag.at.1));
+                    node.is_some()
+                        && node == netlist.node_name(&Point::new(pts[[0, 0]], pts[[0, 1]]))
+                } else {
+                    on_segment(pts, flag.at)
+                }
+            })
+            .and_then(|flag| flag.netclass())
+    }
+    /// plot the netclass directive labels with their fields.
+    fn netclass_flags(&mut self) -> Vec<PlotItem> {
+        let extra = self.extra;
+        let mut items: Vec<PlotItem> = Vec::new();
+        for flag in &extra.netclass_flags {
+            let start = items.len();
+            let stroke = self.stroke("netclass_flag");
+            // the stem points up for angle 0 and turns counter clockwise.
+            let theta = flag.angle.to_radians();
+            let dir = arr1(&[-theta.sin(), -theta.cos()]);
+            let perp = arr1(&[dir[1], -dir[0]]);
+            let at = arr1(&[flag.at.0, flag.at.1]);
+            let end = &at + &dir * flag.length;
+            let size = FLAG_SIZE / 2.0;
+            items.push(PlotItem::Line(
+                10,
+                Line::new(
+                    arr2(&[[at[0], at[1]], [end[0], end[1]]]),
+                    stroke.width,
+                    stroke.linetype.clone(),
+                    LineCap::Butt,
+                    stroke.color,
+                ),
+            ));
+            let outline = |pts: Vec<Array1<f64>>| {
+                points(&pts.iter().map(|pt| (pt[0], pt[1])).collect::<Vec<_>>())
+            };
+            match flag.shape.as_str() {
+                "dot" | "round" => items.push(PlotItem::Circle(
+                    10,
+                    Circle::new(
+                        &end + &dir * size,
+                        size,
+                        stroke.width,
+                        stroke.linetype.clone(),
+                        stroke.color,
+                        if flag.shape == "dot" {
+                            Some(stroke.color)
+                        } else {
+                            None
+                        },
+                    ),
+                )),
+                "diamond" => items.push(PlotItem::Polyline(
+                    10,
+                    Polyline::new(
+                        outline(vec![
+                            end.clone(),
+                            &end + &dir * size + &perp * size,
+                            &end + &dir * (2.0 * size),
+                            &end + &dir * size - &perp * size,
+                            end.clone(),
+                        ]),
+                        stroke.color,
+                        stroke.width,
+                        stroke.linetype.clone(),
+                        None,
+                    ),
+                )),
+                "rectangle" => items.push(PlotItem::Polyline(
+                    10,
+                    Polyline::new(
+                        outline(vec![
+                            &end + &perp * size,
+                            &end + &dir * (2.0 * size) + &perp * size,
+                            &end + &dir * (2.0 * size) - &perp * size,
+                            &end - &perp * size,
+                            &end + &perp * size,
+                        ]),
+                        stroke.color,
+                        stroke.width,
+                        stroke.linetype.clone(),
+                        None,
+                    ),
+                )),
+                shape => self.warnings.push(Warning::UnsupportedElement(format!(
+                    "netclass flag shape {}",
+                    shape
+                ))),
+            }
+            for field in &flag.fields {
+                if field.effects.hide {
+                    continue;
+                }
+                let theme_effects = self.effects("property");
+                let mut effects = Themer::get(&field.effects, &theme_effects);
+                if field.effects.color == (0.0, 0.0, 0.0, 0.0) {
+                    effects.color = theme_effects.color;
+                }
+                items.push(text!(
+                    arr1(&[field.at.0, field.at.1]),
+                    field.angle,
+                    self.variables.resolve(&field.value),
+                    effects
+                ));
+            }
+            self.select_tail(&mut items, start, flag.at);
+        }
+        items
+    }
+}
+
+/// get the end points of the bus entry.
+fn bus_entry_ends(entry: &BusEntry) -> Wire {
+    (
+        (entry.at[0], entry.at[1]),
+        (entry.at[0] + entry.size[0], entry.at[1] + entry.size[1]),
+    )
+}
+
+/// test if the point is on the line segments.
+fn on_segment(pts: &Array2<f64>, pt: (f64, f64)) -> bool {
+    pts.rows().into_iter().tuple_windows().any(|(a, b)| {
+        let cross = (b[0] - a[0]) * (pt.1 - a[1]) - (b[1] - a[1]) * (pt.0 - a[0]);
+        cross.abs() < 1e-6
+            && pt.0 >= a[0].min(b[0]) - 1e-6
+            && pt.0 <= a[0].max(b[0]) + 1e-6
+            && pt.1 >= a[1].min(b[1]) - 1e-6
+            && pt.1 <= a[1].max(b[1]) + 1e-6
+    })
 }
 
 pub trait PlotIterator<T>: Iterator<Item = T> + Sized {
-    fn plot<'a>(self, schema: &'a Schema, title_block: &'a Option<TitleBlock>, paper_size: (f64, f64), theme: &'a Theme, border: bool, netlist: &'a Option<Netlist<'a>>) -> SchemaPlot<'a, Self> {
-        SchemaPlot::new(self, schema, title_block, paper_size, theme, border, netlist)
+    fn plot(self, context: PlotContext<'_>) -> SchemaPlot<'_, Self> {
+        SchemaPlot::new(self, context)
     }
 }
 impl<T, I: Iterator<Item = T>> PlotIterator<T> for I {}
@@ -607,9 +1575,15 @@ impl<T, I: Iterator<Item = T>> PlotIterator<T> for I {}
 mod tests {
     use elektron_sexp::Schema;
     use elektron_spice::Netlist;
+    use ndarray::{arr2, Array2};
+    use std::collections::HashMap;
     use std::path::Path;
 
-    use crate::{plot_schema, plot_schema_buffer, get_plots, store_plot};
+    use super::on_segment;
+
+    use crate::{plot_schema, plot_schema_options};
+    use crate::{page_items, paper, Hide, PlotItem, PlotOptions, StyleRule, Subcircuit, Warning};
+    use crate::{Marker, PageItems, Severity};
 
     #[test]
     fn plt_dco() {
@@ -626,6 +1600,22 @@ mod tests {
         assert!(Path::new("/tmp/dco-mono.svg").metadata().unwrap().len() > 0);
     }
     #[test]
+    fn plt_dco_pdf() {
+        let doc = Schema::load("files/dco.kicad_sch").unwrap();
+        plot_schema(
+            &doc,
+            Some("/tmp/dco.pdf"),
+            3.0,
+            false,
+            "kicad_2000",
+            None,
+            Some("pdf"),
+        )
+        .unwrap();
+        assert!(Path::new("/tmp/dco.pdf").exists());
+        assert!(Path::new("/tmp/dco.pdf").metadata().unwrap().len() > 0);
+    }
+    #[test]
     fn plt_summe() {
         let doc = Schema::load("files/summe.kicad_sch").unwrap();
         plot_schema(&doc, Some("/tmp/summe.svg"), 3.0, true, "kicad_2000", None, Some("svg")).unwrap();
@@ -647,4 +1637,298 @@ mod tests {
         assert!(Path::new("/tmp/summe-netlist.svg").exists());
         assert!(Path::new("/tmp/summe-netlist.svg").metadata().unwrap().len() > 0);
     }
+    #[test]
+    fn plt_summe_hidden() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let netlist = Netlist::from(&doc).unwrap();
+        let mut options = PlotOptions::default();
+        let (full, _) = plot_schema_options(&doc, Some(netlist), &options).unwrap();
+        options.hide = Hide {
+            fields: true,
+            pin_numbers: true,
+            pin_names: true,
+            no_connects: true,
+            notes: true,
+            power_symbols: true,
+            netlist: true,
+            ..Hide::default()
+        };
+        let netlist = Netlist::from(&doc).unwrap();
+        let (hidden, _) = plot_schema_options(&doc, Some(netlist), &options).unwrap();
+        assert!(hidden[0].len() < full[0].len());
+    }
+    #[test]
+    fn plt_paper() {
+        // the schema model does not know the orientation of the paper.
+        let content = std::fs::read_to_string("files/summe.kicad_sch").unwrap();
+        let path = "/tmp/summe-paper.kicad_sch";
+        std::fs::write(
+            path,
+            content.replace("(paper \"A4\")", "(paper \"A3\" portrait)"),
+        )
+        .unwrap();
+        let doc = Schema::load(path).unwrap();
+        let options = PlotOptions {
+            border: true,
+            schema_path: Some(path.to_string()),
+            ..PlotOptions::default()
+        };
+        let (buffers, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert!(warnings.is_empty());
+        let svg = String::from_utf8(buffers[0].clone()).unwrap();
+        let size = |key: &str| -> f64 {
+            let start = svg.find(&format!("{}=\"", key)).unwrap() + key.len() + 2;
+            let end = start + svg[start..].find('"').unwrap();
+            svg[start..end].trim_end_matches("pt").parse().unwrap()
+        };
+        assert!(size("height") > size("width"));
+        // the US letter has the size of the ANSI A paper.
+        let options = PlotOptions {
+            paper: Some(String::from("USLetter")),
+            ..options
+        };
+        let (pages, _) = page_items(&doc, &None, &options).unwrap();
+        assert_eq!(paper::US_LETTER, pages[0].paper_size);
+        assert!(pages[0].items.iter().any(|item| match item {
+            PlotItem::Text(_, text) => text.text == "Size: USLetter",
+            _ => false,
+        }));
+    }
+    #[test]
+    fn plt_graphics() {
+        // the graphics and text boxes are read from the schema file.
+        let doc = Schema::load("files/graphics.kicad_sch").unwrap();
+        let mut options = PlotOptions {
+            border: false,
+            ..PlotOptions::default()
+        };
+        let (buffers, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert_eq!(vec![Warning::MissingSchemaFile], warnings);
+        let svg = String::from_utf8(buffers[0].clone()).unwrap();
+        assert!(!svg.contains("rgb(100%,0%,0%)"));
+        assert!(!svg.contains("rgb(0%,100%,0%)"));
+        // the file name of the title block is used without schema path.
+        options.filename = Some(String::from("files/graphics.kicad_sch"));
+        let (buffers, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let svg = String::from_utf8(buffers[0].clone()).unwrap();
+        assert!(svg.contains("rgb(100%,0%,0%)"));
+        options.filename = None;
+        options.schema_path = Some(String::from("files/graphics.kicad_sch"));
+        let (buffers, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let svg = String::from_utf8(buffers[0].clone()).unwrap();
+        assert!(svg.contains("rgb(100%,0%,0%)"));
+        assert!(svg.contains("rgb(0%,100%,0%)"));
+    }
+    #[test]
+    fn plt_dnp() {
+        // the do not populate flag of KiCad 7 is read from the schema file,
+        // the schema model does not know the flag.
+        let content = std::fs::read_to_string("files/dnp.kicad_sch").unwrap();
+        let path = "/tmp/dnp-model.kicad_sch";
+        std::fs::write(
+            path,
+            content.replace(" (dnp yes)", "").replace(" (dnp no)", ""),
+        )
+        .unwrap();
+        let doc = Schema::load(path).unwrap();
+        let options = PlotOptions {
+            border: false,
+            schema_path: Some(String::from("files/dnp.kicad_sch")),
+            styles: vec![StyleRule::dnp().hide()],
+            ..PlotOptions::default()
+        };
+        let (pages, warnings) = page_items(&doc, &None, &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let texts: Vec<&str> = pages[0]
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlotItem::Text(_, text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(texts.contains(&"R1"));
+        assert!(!texts.contains(&"R2"));
+    }
+    #[test]
+    fn plt_summe_subcircuit() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let netlist = Netlist::from(&doc).unwrap();
+        let (full, _) = plot_schema_options(&doc, Some(netlist), &PlotOptions::default()).unwrap();
+        let options = PlotOptions {
+            schema_path: Some(String::from("files/summe.kicad_sch")),
+            subcircuit: Some(Subcircuit::component("R1").hops(0)),
+            ..PlotOptions::default()
+        };
+        let netlist = Netlist::from(&doc).unwrap();
+        let (selected, warnings) = plot_schema_options(&doc, Some(netlist), &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(selected[0].len() < full[0].len());
+    }
+    #[test]
+    fn plt_netclass() {
+        let doc = Schema::load("files/netclass.kicad_sch").unwrap();
+        let options = PlotOptions {
+            border: false,
+            schema_path: Some(String::from("files/netclass.kicad_sch")),
+            netclass_colors: HashMap::from([(String::from("Audio"), (0.0, 0.0, 1.0, 1.0))]),
+            ..PlotOptions::default()
+        };
+        let (buffers, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let svg = String::from_utf8(buffers[0].clone()).unwrap();
+        // the wire with the flag, the wire color and the flag.
+        assert!(svg.contains("rgb(0%,0%,100%)"));
+        assert!(svg.contains("rgb(100%,0%,100%)"));
+        assert!(svg.contains("rgb(28.235294%,28.235294%,28.235294%)"));
+    }
+    #[test]
+    fn plt_summe_net_legend() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let options = PlotOptions {
+            schema_path: Some(String::from("files/summe.kicad_sch")),
+            net_colors: true,
+            net_legend: true,
+            ..PlotOptions::default()
+        };
+        let netlist = Netlist::from(&doc).unwrap();
+        let (_, warnings) = plot_schema_options(&doc, Some(netlist), &options).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let (_, warnings) = plot_schema_options(&doc, None, &options).unwrap();
+        assert_eq!(
+            vec![Warning::MissingNetlist("net colors")],
+            warnings
+        );
+    }
+    #[test]
+    fn plt_erc_legend() {
+        // the ERC legend is placed in a free corner, the marker takes the top left corner.
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let mut options = PlotOptions {
+            schema_path: Some(String::from("files/summe.kicad_sch")),
+            markers: vec![Marker::new(
+                (20.0, 12.0),
+                Severity::Error,
+                "pin not connected",
+                "/",
+            )],
+            ..PlotOptions::default()
+        };
+        let legends = |pages: &[PageItems]| -> Vec<Array2<f64>> {
+            pages[0]
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    // the net tags have the same background but are lower.
+                    PlotItem::Rectangle(_, rect)
+                        if rect.fill == Some((1.0, 1.0, 1.0, 0.8))
+                            && rect.pts[[1, 1]] - rect.pts[[0, 1]] > 2.0 =>
+                    {
+                        Some(rect.pts.clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        let (pages, _) = page_items(&doc, &None, &options).unwrap();
+        let erc = &legends(&pages)[0];
+        assert!(erc[[0, 0]] > 20.0 || erc[[0, 1]] > 12.0);
+        // the ERC legend does not cover the net legend.
+        options.net_colors = true;
+        options.net_legend = true;
+        let netlist = Netlist::from(&doc).unwrap();
+        let (pages, _) = page_items(&doc, &Some(netlist), &options).unwrap();
+        let legends = legends(&pages);
+        assert_eq!(2, legends.len());
+        let (net, erc) = (&legends[0], &legends[1]);
+        assert!(
+            erc[[0, 0]] > net[[1, 0]]
+                || erc[[1, 0]] < net[[0, 0]]
+                || erc[[0, 1]] > net[[1, 1]]
+                || erc[[1, 1]] < net[[0, 1]]
+        );
+    }
+    #[test]
+    fn bus_entry() {
+        let path = "/tmp/bus-entry.kicad_sch";
+        std::fs::write(
+            path,
+            r#"(kicad_sch (version 20211123) (generator eeschema)
+  (uuid 0b1c2d3e-4f50-4617-8283-94a5b6c7d8e9)
+  (paper "A4")
+  (lib_symbols
+  )
+  (bus_entry (at 50.8 50.8) (size 2.54 -2.54)
+    (stroke (width 0) (type default) (color 0 0 0 0))
+    (uuid 1c2d3e4f-5061-4728-9394-a5b6c7d8e9fa)
+  )
+  (sheet_instances
+    (path "/" (page "1"))
+  )
+)"#,
+        )
+        .unwrap();
+        let doc = Schema::load(path).unwrap();
+        let (pages, _) = page_items(&doc, &None, &PlotOptions::default()).unwrap();
+        let entry = arr2(&[[50.8, 50.8], [53.34, 48.26]]);
+        assert!(pages[0].items.iter().any(|item| match item {
+            PlotItem::Line(_, line) => (&line.pts - &entry).iter().all(|d| d.abs() < 1e-9),
+            _ => false,
+        }));
+    }
+    #[test]
+    fn subcircuit_bus() {
+        // a bus entry on the net of R1 and the bus behind it.
+        let content = std::fs::read_to_string("files/summe.kicad_sch").unwrap();
+        let path = "/tmp/summe-bus.kicad_sch";
+        let end = content.find("  (sheet_instances").unwrap();
+        std::fs::write(
+            path,
+            format!(
+                r#"{}  (bus_entry (at 66.04 176.53) (size 2.54 -2.54)
+    (stroke (width 0) (type default) (color 0 0 0 0))
+    (uuid 2d3e4f50-6172-4839-a4b5-c6d7e8f90a1b)
+  )
+  (bus (pts (xy 68.58 173.99) (xy 68.58 160.02))
+    (stroke (width 0) (type default) (color 0 0 0 0))
+    (uuid 3e4f5061-7283-494a-b5c6-d7e8f90a1b2c)
+  )
+{}"#,
+                &content[..end],
+                &content[end..]
+            ),
+        )
+        .unwrap();
+        // the netlist does not support buses, it is taken from the schema without them.
+        let summe = Schema::load("files/summe.kicad_sch").unwrap();
+        let netlist = Netlist::from(&summe).unwrap();
+        let doc = Schema::load(path).unwrap();
+        let options = PlotOptions {
+            border: false,
+            subcircuit: Some(Subcircuit::component("R1").hops(0)),
+            ..PlotOptions::default()
+        };
+        let (pages, _) = page_items(&doc, &Some(netlist), &options).unwrap();
+        let drawn = |pts: Array2<f64>| {
+            pages[0].items.iter().any(|item| match item {
+                PlotItem::Line(_, line) => {
+                    line.pts.shape() == pts.shape()
+                        && (&line.pts - &pts).iter().all(|d| d.abs() < 1e-9)
+                }
+                _ => false,
+            })
+        };
+        assert!(drawn(arr2(&[[66.04, 176.53], [68.58, 173.99]])));
+        assert!(drawn(arr2(&[[68.58, 173.99], [68.58, 160.02]])));
+    }
+    #[test]
+    fn point_on_wire() {
+        let pts = arr2(&[[10.0, 10.0], [10.0, 20.0], [30.0, 20.0]]);
+        assert!(on_segment(&pts, (10.0, 15.0)));
+        assert!(on_segment(&pts, (25.0, 20.0)));
+        assert!(!on_segment(&pts, (15.0, 15.0)));
+        assert!(!on_segment(&pts, (10.0, 25.0)));
+    }
 }
@@ -0,0 +1,101 @@
+use elektron_sexp::parser::{SexpParser, State};
+
+/// A s-expression node built from the states of the `SexpParser`.
+///
+/// Used for KiCad files and elements which are not part of the elektron_sexp model.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Node {
+    pub name: String,
+    pub values: Vec<String>,
+    pub nodes: Vec<Node>,
+}
+
+impl Node {
+    /// parse the content and return the root node.
+    pub fn parse(content: String) -> Option<Node> {
+        let doc = SexpParser::from(content);
+        let mut stack: Vec<Node> = Vec::new();
+        for state in doc.iter() {
+            match state {
+                State::StartSymbol(name) => stack.push(Node {
+                    name: name.to_string(),
+                    ..Default::default()
+                }),
+                State::EndSymbol => {
+                    let node = stack.pop()?;
+                    if let Some(parent) = stack.last_mut() {
+                        parent.nodes.push(node);
+                    } else {
+                        return Some(node);
+                    }
+                }
+                State::Values(value) => stack.last_mut()?.values.push(value.to_string()),
+                State::Text(value) => stack.last_mut()?.values.push(value.to_string()),
+            }
+        }
+        None
+    }
+    /// get the first child node with the name.
+    pub fn get(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+    /// get all child nodes with the name.
+    pub fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.nodes.iter().filter(move |n| n.name == name)
+    }
+    pub fn value(&self, index: usize) -> Option<&str> {
+        self.values.get(index).map(|v| v.as_str())
+    }
+    pub fn f64(&self, index: usize) -> Option<f64> {
+        self.values.get(index)?.parse().ok()
+    }
+    /// get the first value of the child node as f64, like `(width 0.15)`.
+    pub fn child_f64(&self, name: &str) -> Option<f64> {
+        self.get(name)?.f64(0)
+    }
+    /// test for a flag value, like `hide` or `portrait`.
+    pub fn has(&self, value: &str) -> bool {
+        self.values.iter().any(|v| v == value)
+    }
+    /// test for a flag, a value like `hide` in KiCad 7 or a child like `(hide yes)` in KiCad 8.
+    pub fn flag(&self, name: &str) -> bool {
+        self.has(name) || self.get(name).and_then(|n| n.value(0)) == Some("yes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Node;
+
+    #[test]
+    fn parse_nodes() {
+        let node = Node::parse(String::from(
+            r#"(kicad_wks (setup (textsize 1.5 1.5) (left_margin 10)) (tbtext "Title" (pos 109 10.7 rbcorner)))"#,
+        ))
+        .unwrap();
+        assert_eq!("kicad_wks", node.name);
+        assert_eq!(
+            Some(10.0),
+            node.get("setup").unwrap().child_f64("left_margin")
+        );
+        let text = node.get("tbtext").unwrap();
+        assert_eq!(Some("Title"), text.value(0));
+        assert!(text.get("pos").unwrap().has("rbcorner"));
+    }
+    #[test]
+    fn flags() {
+        let node = Node::parse(String::from(
+            r#"(effects (font (size 1.27 1.27) (bold yes) italic) (hide yes))"#,
+        ))
+        .unwrap();
+        assert!(node.flag("hide"));
+        assert!(node.get("font").unwrap().flag("bold"));
+        assert!(node.get("font").unwrap().flag("italic"));
+        let node = Node::parse(String::from(
+            r#"(effects (font (size 1.27 1.27)) (hide no))"#,
+        ))
+        .unwrap();
+        assert!(!node.flag("hide"));
+        assert!(!node.flag("justify"));
+    }
+}
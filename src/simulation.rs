@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+/// the SI prefixes from femto to tera.
+const PREFIXES: [(f64, &str); 9] = [
+    (1e12, "T"),
+    (1e9, "G"),
+    (1e6, "M"),
+    (1e3, "k"),
+    (1.0, ""),
+    (1e-3, "m"),
+    (1e-6, "µ"),
+    (1e-9, "n"),
+    (1e-12, "p"),
+];
+
+/// format the value with a SI prefix and the unit.
+///
+/// The precision is the number of significant digits, trailing zeros are removed.
+pub fn format_si(value: f64, unit: &str, precision: usize) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format!("{}{}", if value.is_finite() { 0.0 } else { value }, unit);
+    }
+    let (scale, prefix) = PREFIXES
+        .iter()
+        .find(|(scale, _)| value.abs() >= *scale * (1.0 - 1e-12))
+        .unwrap_or(&PREFIXES[PREFIXES.len() - 1]);
+    let scaled = value / scale;
+    let digits = scaled.abs().log10().floor() as i32 + 1;
+    let decimals = (precision.max(1) as i32 - digits).max(0) as usize;
+    // the rounded value can reach the next prefix, 999.96 is 1k and not 1000.
+    let rounded = (scaled * 10f64.powi(decimals as i32)).round() / 10f64.powi(decimals as i32);
+    if rounded.abs() >= 1000.0 && *scale < PREFIXES[0].0 {
+        return format_si(rounded * scale, unit, precision);
+    }
+    let mut number = format!("{:.*}", decimals, scaled);
+    if number.contains('.') {
+        number = number
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string();
+    }
+    format!("{}{}{}", number, prefix, unit)
+}
+
+/// get the color of the voltage in the range, from blue at the minimum to red at the maximum.
+pub fn voltage_color(value: f64, range: (f64, f64)) -> (f64, f64, f64, f64) {
+    let t = if range.1 > range.0 {
+        ((value - range.0) / (range.1 - range.0)).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    (t, 0.2 * (1.0 - (2.0 * t - 1.0).abs()), 1.0 - t, 1.0)
+}
+
+/// get the value at the position, linear interpolated between the samples.
+pub fn sample(x: &[f64], y: &[f64], at: f64) -> Option<f64> {
+    let len = x.len().min(y.len());
+    if len == 0 {
+        return None;
+    }
+    let i = x[..len].partition_point(|v| *v < at);
+    if i == 0 {
+        Some(y[0])
+    } else if i == len {
+        Some(y[len - 1])
+    } else if x[i] > x[i - 1] {
+        let t = (at - x[i - 1]) / (x[i] - x[i - 1]);
+        Some(y[i - 1] + t * (y[i] - y[i - 1]))
+    } else {
+        Some(y[i])
+    }
+}
+
+/// The results of a SPICE operating point analysis.
+///
+/// The node voltages are stored by the node name and the branch currents
+/// by the component reference, both in lower case.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperatingPoint {
+    pub voltages: HashMap<String, f64>,
+    pub currents: HashMap<String, f64>,
+}
+
+impl OperatingPoint {
+    /// get the operating point from the vectors of the simulation.
+    ///
+    /// Vectors like `v(out)` or `out` are node voltages, `v1#branch` and
+    /// `@r1[i]` are the currents of the components.
+    pub fn from(vectors: &HashMap<String, Vec<f64>>) -> Self {
+        let mut op = OperatingPoint::default();
+        for (name, values) in vectors {
+            let value = match values.first() {
+                Some(value) => *value,
+                None => continue,
+            };
+            let name = name.to_lowercase();
+            if let Some(branch) = name.strip_suffix("#branch") {
+                op.currents.insert(branch.to_string(), value);
+            } else if let Some(device) = name.strip_prefix('@').and_then(|n| n.strip_suffix("[i]"))
+            {
+                op.currents.insert(device.to_string(), value);
+            } else if let Some(node) = name.strip_prefix("v(").and_then(|n| n.strip_suffix(')')) {
+                op.voltages.insert(node.to_string(), value);
+            } else if !name.starts_with('@') && !name.starts_with("i(") {
+                op.voltages.insert(name, value);
+            }
+        }
+        op
+    }
+    /// get the voltage of the node.
+    pub fn voltage(&self, node: &str) -> Option<f64> {
+        self.voltages.get(&node.to_lowercase()).copied()
+    }
+    /// get the current through the component.
+    pub fn current(&self, reference: &str) -> Option<f64> {
+        self.currents.get(&reference.to_lowercase()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{format_si, sample, voltage_color, OperatingPoint};
+
+    #[test]
+    fn si_units() {
+        assert_eq!("12.3mV", format_si(0.012345, "V", 3));
+        assert_eq!("-1.5µA", format_si(-0.0000015, "A", 3));
+        assert_eq!("4.7kΩ", format_si(4700.0, "Ω", 2));
+        assert_eq!("5V", format_si(5.0, "V", 3));
+        assert_eq!("0V", format_si(0.0, "V", 3));
+        assert_eq!("123V", format_si(123.456, "V", 2));
+        assert_eq!("1kV", format_si(999.96, "V", 3));
+        assert_eq!("-1mA", format_si(-0.00099999, "A", 3));
+    }
+    #[test]
+    fn transient_samples() {
+        let (x, y) = ([0.0, 1.0, 2.0], [0.0, 10.0, 0.0]);
+        assert_eq!(Some(5.0), sample(&x, &y, 0.5));
+        assert_eq!(Some(10.0), sample(&x, &y, 1.0));
+        assert_eq!(Some(0.0), sample(&x, &y, 3.0));
+        assert_eq!(None, sample(&[], &[], 1.0));
+        assert_eq!((0.0, 0.0, 1.0, 1.0), voltage_color(-1.0, (0.0, 5.0)));
+        assert_eq!((1.0, 0.0, 0.0, 1.0), voltage_color(5.0, (0.0, 5.0)));
+    }
+    #[test]
+    fn operating_point() {
+        let vectors = HashMap::from([
+            (String::from("v(out)"), vec![2.5]),
+            (String::from("IN"), vec![5.0]),
+            (String::from("v1#branch"), vec![-0.001]),
+            (String::from("@R1[i]"), vec![0.0025]),
+        ]);
+        let op = OperatingPoint::from(&vectors);
+        assert_eq!(Some(2.5), op.voltage("OUT"));
+        assert_eq!(Some(5.0), op.voltage("in"));
+        assert_eq!(Some(-0.001), op.current("V1"));
+        assert_eq!(Some(0.0025), op.current("R1"));
+        assert_eq!(None, op.voltage("v1#branch"));
+    }
+}
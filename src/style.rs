@@ -0,0 +1,220 @@
+use crate::error::Error;
+use elektron_sexp::{Effects, Stroke, Symbol};
+use regex::Regex;
+
+/// The symbols a style rule applies to.
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// the library id, like `power:*`.
+    LibId(Regex),
+    /// the reference prefix, like `U`.
+    Reference(String),
+    /// the property exists and the value matches the pattern.
+    Property(String, Option<Regex>),
+    /// the symbol is marked as do not populate.
+    Dnp,
+}
+
+/// The overrides of a style rule, unset values keep the theme style.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Style {
+    pub color: Option<(f64, f64, f64, f64)>,
+    pub width: Option<f64>,
+    /// the color of the filled shapes.
+    pub fill: Option<(f64, f64, f64, f64)>,
+    /// the alpha factor of the colors.
+    pub opacity: Option<f64>,
+    pub hide: bool,
+}
+
+/// Style the symbols by the library id, the reference or a property.
+///
+/// The rules are applied in order on top of the theme, a later rule overrides
+/// the values of the earlier rules.
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    matcher: Matcher,
+    pub style: Style,
+}
+
+impl StyleRule {
+    fn new(matcher: Matcher) -> Self {
+        Self {
+            matcher,
+            style: Style::default(),
+        }
+    }
+    /// match the library id with a glob pattern, `*` and `?` are wildcards.
+    pub fn lib_id(glob: &str) -> Result<Self, Error> {
+        let mut pattern = String::from("^");
+        for c in glob.chars() {
+            match c {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                c => pattern.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        pattern.push('$');
+        let regex = Regex::new(&pattern)
+            .map_err(|err| Error::Pattern(glob.to_string(), err.to_string()))?;
+        Ok(Self::new(Matcher::LibId(regex)))
+    }
+    /// match the reference prefix, like `U` for `U1`.
+    pub fn reference(prefix: &str) -> Self {
+        Self::new(Matcher::Reference(prefix.to_string()))
+    }
+    /// match the symbols with the property, like `DNP`.
+    pub fn property(key: &str) -> Self {
+        Self::new(Matcher::Property(key.to_string(), None))
+    }
+    /// match the symbols marked as do not populate in KiCad 7 and later.
+    pub fn dnp() -> Self {
+        Self::new(Matcher::Dnp)
+    }
+    /// match the property value with a regular expression, like `^NC`.
+    pub fn property_value(key: &str, pattern: &str) -> Result<Self, Error> {
+        let regex = Regex::new(pattern)
+            .map_err(|err| Error::Pattern(pattern.to_string(), err.to_string()))?;
+        Ok(Self::new(Matcher::Property(key.to_string(), Some(regex))))
+    }
+    pub fn color(mut self, color: (f64, f64, f64, f64)) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+    pub fn width(mut self, width: f64) -> Self {
+        self.style.width = Some(width);
+        self
+    }
+    pub fn fill(mut self, fill: (f64, f64, f64, f64)) -> Self {
+        self.style.fill = Some(fill);
+        self
+    }
+    pub fn opacity(mut self, opacity: f64) -> Self {
+        self.style.opacity = Some(opacity);
+        self
+    }
+    pub fn hide(mut self) -> Self {
+        self.style.hide = true;
+        self
+    }
+    /// test if the rule applies to the symbol, `dnp` is the do not populate flag of the symbol.
+    pub fn matches(&self, symbol: &Symbol, dnp: bool) -> bool {
+        self.is_match(&symbol.lib_id, dnp, |key| {
+            symbol
+                .property
+                .iter()
+                .find(|p| p.key == key)
+                .map(|p| p.value.as_str())
+        })
+    }
+    fn is_match<'a>(
+        &self,
+        lib_id: &str,
+        dnp: bool,
+        property: impl Fn(&str) -> Option<&'a str>,
+    ) -> bool {
+        match &self.matcher {
+            Matcher::LibId(regex) => regex.is_match(lib_id),
+            // the prefix is followed by the number, `R` does not match `RV1`.
+            Matcher::Reference(prefix) => property("Reference")
+                .and_then(|reference| reference.strip_prefix(prefix.as_str()))
+                .and_then(|number| number.chars().next())
+                .map(|c| c.is_ascii_digit() || c == '?')
+                .unwrap_or(false),
+            Matcher::Property(key, pattern) => property(key)
+                .map(|value| pattern.as_ref().map(|r| r.is_match(value)).unwrap_or(true))
+                .unwrap_or(false),
+            Matcher::Dnp => dnp,
+        }
+    }
+}
+
+impl Style {
+    /// merge the matching rules for the symbol.
+    pub(crate) fn from(rules: &[StyleRule], symbol: &Symbol, dnp: bool) -> Self {
+        let mut style = Style::default();
+        for rule in rules.iter().filter(|r| r.matches(symbol, dnp)) {
+            let other = &rule.style;
+            style.color = other.color.or(style.color);
+            style.width = other.width.or(style.width);
+            style.fill = other.fill.or(style.fill);
+            style.opacity = other.opacity.or(style.opacity);
+            style.hide |= other.hide;
+        }
+        style
+    }
+    fn alpha(&self, color: (f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
+        match self.opacity {
+            Some(opacity) => (color.0, color.1, color.2, color.3 * opacity),
+            None => color,
+        }
+    }
+    /// apply the style to the stroke.
+    pub(crate) fn stroke(&self, mut stroke: Stroke) -> Stroke {
+        stroke.color = self.alpha(self.color.unwrap_or(stroke.color));
+        stroke.width = self.width.unwrap_or(stroke.width);
+        stroke
+    }
+    /// apply the style to the text effects.
+    pub(crate) fn effects(&self, mut effects: Effects) -> Effects {
+        effects.color = self.alpha(self.color.unwrap_or(effects.color));
+        effects
+    }
+    /// apply the style to the fill color, unfilled shapes stay unfilled.
+    pub(crate) fn fill_color(
+        &self,
+        fill: Option<(f64, f64, f64, f64)>,
+    ) -> Option<(f64, f64, f64, f64)> {
+        fill.map(|color| self.alpha(self.fill.unwrap_or(color)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Style, StyleRule};
+    use elektron_sexp::Stroke;
+
+    #[test]
+    fn rules() {
+        let none = |_: &str| None;
+        let rule = StyleRule::lib_id("power:*").unwrap();
+        assert!(rule.is_match("power:GND", false, none));
+        assert!(!rule.is_match("Device:R", false, none));
+        let rule = StyleRule::dnp();
+        assert!(rule.is_match("Device:R", true, none));
+        assert!(!rule.is_match("Device:R", false, |key| (key == "DNP").then_some("")));
+        let rule = StyleRule::lib_id("Amplifier_Operational:TL07?").unwrap();
+        assert!(rule.is_match("Amplifier_Operational:TL072", false, none));
+        assert!(!rule.is_match("Amplifier_Operational:NE5532", false, none));
+        let rule = StyleRule::reference("R");
+        assert!(rule.is_match("Device:R", false, |_| Some("R12")));
+        assert!(!rule.is_match("Device:R_Pot", false, |_| Some("RV1")));
+        let rule = StyleRule::property("DNP");
+        assert!(rule.is_match("Device:R", false, |key| (key == "DNP").then_some("")));
+        assert!(!rule.is_match("Device:R", false, none));
+        let rule = StyleRule::property_value("Value", "^NC").unwrap();
+        assert!(rule.is_match("Device:R", false, |_| Some("NC_10k")));
+        assert!(!rule.is_match("Device:R", false, |_| Some("10k")));
+        assert!(StyleRule::property_value("Value", "^(NC").is_err());
+    }
+    #[test]
+    fn apply() {
+        let style = Style {
+            color: Some((1.0, 0.0, 0.0, 1.0)),
+            opacity: Some(0.5),
+            ..Style::default()
+        };
+        let stroke = style.stroke(Stroke {
+            width: 0.254,
+            linetype: String::from("default"),
+            color: (0.0, 0.0, 0.0, 1.0),
+        });
+        assert_eq!((1.0, 0.0, 0.0, 0.5), stroke.color);
+        assert_eq!(0.254, stroke.width);
+        assert_eq!(None, style.fill_color(None));
+        assert_eq!(
+            Some((0.0, 0.0, 1.0, 0.5)),
+            style.fill_color(Some((0.0, 0.0, 1.0, 1.0)))
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::error::Error;
+use elektron_sexp::{LibrarySymbol, Schema, SchemaElement, Shape, Transform};
+use elektron_spice::{Netlist, Point};
+
+/// The start of the sub-circuit.
+#[derive(Debug, Clone, PartialEq)]
+enum Start {
+    Net(String),
+    Component(String),
+}
+
+/// Select the part of the schema around a net or a component.
+///
+/// The components on the net or the component itself are selected first, every
+/// hop adds the components which share a net with the selection. Power nets
+/// are not followed, only their wires and symbols at the pins of the selected
+/// components are drawn. The other items are removed or ghosted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subcircuit {
+    start: Start,
+    pub hops: usize,
+    /// draw the other items with this alpha instead of removing them.
+    pub ghost: Option<f64>,
+}
+
+/// The references and nets of the sub-circuit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub references: HashSet<String>,
+    /// the nets of the selected components without the power nets.
+    pub nets: HashSet<String>,
+}
+
+/// A symbol with the reference, the power flag and the nets of the pins.
+pub(crate) type SymbolNets = (String, bool, Vec<String>);
+
+impl Subcircuit {
+    /// select everything connected to the net.
+    pub fn net(name: &str) -> Self {
+        Self {
+            start: Start::Net(name.to_string()),
+            hops: 0,
+            ghost: None,
+        }
+    }
+    /// select the component and its neighbours.
+    pub fn component(reference: &str) -> Self {
+        Self {
+            start: Start::Component(reference.to_string()),
+            hops: 1,
+            ghost: None,
+        }
+    }
+    pub fn hops(mut self, hops: usize) -> Self {
+        self.hops = hops;
+        self
+    }
+    pub fn ghost(mut self, alpha: f64) -> Self {
+        self.ghost = Some(alpha);
+        self
+    }
+    /// select the components and nets from the nets of the symbols.
+    pub(crate) fn select(&self, symbols: &[SymbolNets]) -> Selection {
+        // the nets of the components, units of a symbol are merged.
+        let mut components: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut power: HashSet<&str> = HashSet::new();
+        for (reference, is_power, nets) in symbols {
+            if *is_power {
+                power.extend(nets.iter().map(|n| n.as_str()));
+            } else {
+                components
+                    .entry(reference.as_str())
+                    .or_default()
+                    .extend(nets.iter().map(|n| n.as_str()));
+            }
+        }
+        let touching = |nets: &HashSet<&str>| -> Vec<&str> {
+            components
+                .iter()
+                .filter(|(_, pins)| pins.iter().any(|n| nets.contains(n)))
+                .map(|(reference, _)| *reference)
+                .collect()
+        };
+        let mut selected: HashSet<&str> = HashSet::new();
+        match &self.start {
+            Start::Net(name) => {
+                selected.extend(touching(&HashSet::from([name.as_str()])));
+            }
+            Start::Component(reference) => {
+                if components.contains_key(reference.as_str()) {
+                    selected.insert(reference.as_str());
+                }
+            }
+        }
+        for _ in 0..self.hops {
+            let nets: HashSet<&str> = selected
+                .iter()
+                .flat_map(|reference| components[reference].iter().copied())
+                .filter(|net| !power.contains(net))
+                .collect();
+            selected.extend(touching(&nets));
+        }
+        let mut nets: HashSet<String> = selected
+            .iter()
+            .flat_map(|reference| components[reference].iter())
+            .filter(|net| !power.contains(*net))
+            .map(|net| net.to_string())
+            .collect();
+        if let Start::Net(name) = &self.start {
+            nets.insert(name.clone());
+        }
+        Selection {
+            references: selected.into_iter().map(|r| r.to_string()).collect(),
+            nets,
+        }
+    }
+}
+
+/// get the nets of the symbol pins on all pages.
+pub(crate) fn symbol_nets(
+    schema: &Schema,
+    library: &HashMap<String, LibrarySymbol>,
+    netlist: &Netlist,
+) -> Result<Vec<SymbolNets>, Error> {
+    let mut symbols = Vec::new();
+    for page in 0..schema.pages() {
+        for element in schema.iter(page)? {
+            if let SchemaElement::Symbol(symbol) = element {
+                if !symbol.on_schema {
+                    continue;
+                }
+                let lib = match schema
+                    .get_library(&symbol.lib_id)
+                    .or_else(|| library.get(&symbol.lib_id))
+                {
+                    Some(lib) => lib,
+                    None => continue,
+                };
+                let reference = symbol
+                    .property
+                    .iter()
+                    .find(|p| p.key == "Reference")
+                    .map(|p| p.value.clone())
+                    .unwrap_or_default();
+                let mut nets = Vec::new();
+                for unit in &lib.symbols {
+                    if unit.unit == 0 || unit.unit == symbol.unit {
+                        for pin in &unit.pin {
+                            let pos = Shape::transform(symbol, &pin.at);
+                            if let Some(net) = netlist.node_name(&Point::new(pos[0], pos[1])) {
+                                nets.push(net);
+                            }
+                        }
+                    }
+                }
+                symbols.push((reference, lib.power, nets));
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+/// get the pin positions of the selected symbols on the page.
+pub(crate) fn selected_pins(
+    schema: &Schema,
+    page: usize,
+    library: &HashMap<String, LibrarySymbol>,
+    selection: &Selection,
+) -> Result<Vec<(f64, f64)>, Error> {
+    let mut pins = Vec::new();
+    for element in schema.iter(page)? {
+        if let SchemaElement::Symbol(symbol) = element {
+            let selected = symbol
+                .property
+                .iter()
+                .any(|p| p.key == "Reference" && selection.references.contains(&p.value));
+            if !selected {
+                continue;
+            }
+            if let Some(lib) = schema
+                .get_library(&symbol.lib_id)
+                .or_else(|| library.get(&symbol.lib_id))
+            {
+                for unit in &lib.symbols {
+                    if unit.unit == 0 || unit.unit == symbol.unit {
+                        for pin in &unit.pin {
+                            let pos = Shape::transform(symbol, &pin.at);
+                            pins.push((pos[0], pos[1]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(pins)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{Subcircuit, SymbolNets};
+
+    fn symbols() -> Vec<SymbolNets> {
+        let symbol = |reference: &str, power: bool, nets: &[&str]| {
+            (
+                reference.to_string(),
+                power,
+                nets.iter().map(|n| n.to_string()).collect(),
+            )
+        };
+        vec![
+            symbol("U1", false, &["VREF", "OUT", "GND"]),
+            symbol("R1", false, &["VCC", "VREF"]),
+            symbol("R2", false, &["VREF", "GND"]),
+            symbol("R3", false, &["OUT", "X"]),
+            symbol("C1", false, &["X", "GND"]),
+            symbol("#PWR01", true, &["GND"]),
+        ]
+    }
+    fn references(refs: &[&str]) -> HashSet<String> {
+        refs.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn net() {
+        let selection = Subcircuit::net("VREF").select(&symbols());
+        assert_eq!(references(&["U1", "R1", "R2"]), selection.references);
+        assert!(selection.nets.contains("VREF"));
+        assert!(!selection.nets.contains("GND"));
+    }
+    #[test]
+    fn hops() {
+        let selection = Subcircuit::component("R3").hops(0).select(&symbols());
+        assert_eq!(references(&["R3"]), selection.references);
+        let selection = Subcircuit::component("R3").select(&symbols());
+        assert_eq!(references(&["R3", "U1", "C1"]), selection.references);
+        // the ground net is not followed.
+        let selection = Subcircuit::component("C1").select(&symbols());
+        assert_eq!(references(&["C1", "R3"]), selection.references);
+        assert_eq!(
+            HashSet::new(),
+            Subcircuit::component("U9").select(&symbols()).references
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use crate::error::Error;
+use crate::error::{Error, Warning};
+use crate::style::Style;
 use elektron_sexp::{color, Effects, Stroke};
 use elektron_sexp::parser::{SexpParser, State};
 #[derive(Debug)]
@@ -73,36 +74,54 @@ impl ThemerMerge<Effects> for Themer {
     }
 }
 
+/// merge the element and theme style and apply the style rules on top.
+pub trait ThemerStyle<T> {
+    fn styled(a: &T, b: &T, style: &Style) -> T;
+}
+impl ThemerStyle<Stroke> for Themer {
+    fn styled(a: &Stroke, b: &Stroke, style: &Style) -> Stroke {
+        style.stroke(Themer::get(a, b))
+    }
+}
+impl ThemerStyle<Effects> for Themer {
+    fn styled(a: &Effects, b: &Effects, style: &Style) -> Effects {
+        style.effects(Themer::get(a, b))
+    }
+}
+
 pub struct Theme {
     items: HashMap<String, ThemeItems>,
+    warnings: Vec<Warning>,
 }
 
 impl Theme {
     fn new(content: String) -> Self {
         let mut items: HashMap<String, ThemeItems> = HashMap::new();
+        let mut warnings = Vec::new();
         let doc = SexpParser::from(content);
         let mut iter = doc.iter();
         loop {
             match iter.next() {
-                Some(State::StartSymbol(name)) => {
-                    if name != "theme" {
-                        let next = iter.next();
-                        if let Some(State::StartSymbol(element)) = next {
-                            if element == "stroke" {
-                                items.insert(
-                                    name.to_string(),
-                                    ThemeItems::Stroke(Stroke::from(&mut iter)),
-                                );
-                            } else if element == "effects" {
-                                items.insert(
-                                    name.to_string(),
-                                    ThemeItems::Effects(Effects::from(&mut iter)),
-                                );
-                            } else if element == "color" {
-                                items.insert(name.to_string(), ThemeItems::Color(color!(iter)));
-                            } else {
-                                todo!("symbol item not implemented: {}", name);
-                            }
+                Some(State::StartSymbol(name)) if name != "theme" => {
+                    let next = iter.next();
+                    if let Some(State::StartSymbol(element)) = next {
+                        if element == "stroke" {
+                            items.insert(
+                                name.to_string(),
+                                ThemeItems::Stroke(Stroke::from(&mut iter)),
+                            );
+                        } else if element == "effects" {
+                            items.insert(
+                                name.to_string(),
+                                ThemeItems::Effects(Effects::from(&mut iter)),
+                            );
+                        } else if element == "color" {
+                            items.insert(name.to_string(), ThemeItems::Color(color!(iter)));
+                        } else {
+                            warnings.push(Warning::UnknownThemeItem(
+                                name.to_string(),
+                                element.to_string(),
+                            ));
                         }
                     }
                 }
@@ -112,7 +131,7 @@ impl Theme {
                 _ => {}
             }
         }
-        Theme { items }
+        Theme { items, warnings }
     }
     pub fn kicad_2000() -> Theme {
         let content = r#"(theme
@@ -120,6 +139,8 @@ impl Theme {
             (wire (stroke (width 0.254) (type default) (color 0 0.5882352941176471 0 1)))
             (junction (stroke (width 0.254) (type default) (color 0 0.5882352941176471 0 1)))
             (no_connect (stroke (width 0.254) (type default) (color 0 0 0.5176470588235295 1)))
+            (notes (stroke (width 0.254) (type default) (color 0 0 0.5176470588235295 1)))
+            (netclass_flag (stroke (width 0.254) (type default) (color 0.2823529411764706 0.2823529411764706 0.2823529411764706 1)))
             (symbol (stroke (width 0.254) (type default) (color 0.5176470588235295 0 0 1)))
             (pin (stroke (width 0.254) (type default) (color 0.5176470588235295 0 0 1)))
             (border_stroke (stroke (width 0.254) (type default) (color 0 0 0 1)))
@@ -128,8 +149,11 @@ impl Theme {
             (property (effects (font (size 1.27 1.27))))
             (pin_number (effects (font (size 0.8 0.8))))
             (pin_name (effects (font (size 1.27 1.27) (color 0 0 0 1))))
+            (node_name (effects (font (size 1.0 1.0) (color 0.4 0.4 0.4 1))))
+            (operating_point (effects (font (size 1.0 1.0) (color 0 0 0.6 1))))
             (text (effects (font (size 5.0 5.0))))
-            (border_effects (effects (font (size 2.54 2.54)) (justify left)))
+            (text_box (effects (font (size 1.27 1.27) (color 0 0 0.5176470588235295 1))))
+            (border_effects (effects (font (size 1.5 1.5))))
             (subtitle_effects (effects (font (size 2.54 2.54)) (justify left)))
             (title_effects (effects (font (size 2.54 2.54) bold) (justify left)))
             (footprint (effects (font (size 5.0 5.0))))
@@ -157,6 +181,8 @@ impl Theme {
             (wire (stroke (width 0.254) (type default) (color 0 0 0 1)))
             (junction (stroke (width 0.254) (type default) (color 0 0 0 1)))
             (no_connect (stroke (width 0.254) (type default) (color 0 0 0 1)))
+            (notes (stroke (width 0.254) (type default) (color 0 0 0 1)))
+            (netclass_flag (stroke (width 0.254) (type default) (color 0 0 0 1)))
             (symbol (stroke (width 0.254) (type default) (color 0 0 0 1)))
             (pin (stroke (width 0.254) (type default) (color 0 0 0 1)))
             (border_stroke (stroke (width 0.254) (type default) (color 0 0 0 1)))
@@ -165,8 +191,11 @@ impl Theme {
             (property (effects (font (size 1.27 1.27))))
             (pin_number (effects (font (size 0.8 0.8))))
             (pin_name (effects (font (size 1.27 1.27) (color 0 0 0 1))))
+            (node_name (effects (font (size 1.0 1.0) (color 0.4 0.4 0.4 1))))
+            (operating_point (effects (font (size 1.0 1.0) (color 0 0 0 1))))
             (text (effects (font (size 5.0 5.0))))
-            (border_effects (effects (font (size 2.54 2.54))))
+            (text_box (effects (font (size 1.27 1.27) (color 0 0 0 1))))
+            (border_effects (effects (font (size 1.5 1.5))))
             (subtitle_effects (effects (font (size 2.54 2.54))))
             (title_effects (effects (font (size 5.0 5.0))))
 
@@ -189,6 +218,44 @@ impl Theme {
             Err(Error::Theme("effects".to_string(), name.to_string()))
         }
     }
+    /// get the stroke, a missing theme item is reported and a default stroke returned.
+    pub fn stroke_or_default(&self, name: &str, warnings: &mut Vec<Warning>) -> Stroke {
+        self.stroke(name).unwrap_or_else(|_| {
+            warnings.push(Warning::MissingThemeKey(
+                "stroke".to_string(),
+                name.to_string(),
+            ));
+            Stroke {
+                width: 0.254,
+                linetype: "default".to_string(),
+                color: (0.0, 0.0, 0.0, 1.0),
+            }
+        })
+    }
+    /// get the effects, a missing theme item is reported and default effects returned.
+    pub fn effects_or_default(&self, name: &str, warnings: &mut Vec<Warning>) -> Effects {
+        self.effects(name).unwrap_or_else(|_| {
+            warnings.push(Warning::MissingThemeKey(
+                "effects".to_string(),
+                name.to_string(),
+            ));
+            Effects {
+                font: String::new(),
+                color: (0.0, 0.0, 0.0, 1.0),
+                font_size: (1.27, 1.27),
+                thickness: 0.0,
+                bold: false,
+                italic: false,
+                line_spacing: 0.0,
+                justify: Vec::new(),
+                hide: false,
+            }
+        })
+    }
+    /// the theme items that could not be parsed.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
     pub fn color(&self, name: &str) -> Option<(f64, f64, f64, f64)> {
         if let Some(ThemeItems::Color(color)) = &self.items.get(name) {
             Some(*color)
@@ -202,6 +269,7 @@ impl Theme {
 mod tests {
 
     use super::Theme;
+    use crate::error::Warning;
 
     #[test]
     fn themes() {
@@ -218,4 +286,34 @@ mod tests {
             theme.stroke("no_connect").unwrap().linetype
         );
     }
+    #[test]
+    fn missing_theme_item() {
+        let theme = Theme::new(String::from("(theme)"));
+        let mut warnings = Vec::new();
+        assert_eq!(0.254, theme.stroke_or_default("wire", &mut warnings).width);
+        assert_eq!(
+            vec![Warning::MissingThemeKey(
+                String::from("stroke"),
+                String::from("wire")
+            )],
+            warnings
+        );
+    }
+    #[test]
+    fn unsupported_theme_item() {
+        let theme = Theme::new(String::from(
+            r#"(theme
+            (wire (stroke (width 0.254) (type default) (color 0 0 0 1)))
+            (junction (radius 0.5))
+            )"#,
+        ));
+        assert!(theme.stroke("wire").is_ok());
+        assert_eq!(
+            vec![Warning::UnknownThemeItem(
+                String::from("junction"),
+                String::from("radius")
+            )],
+            theme.warnings()
+        );
+    }
 }
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use crate::border::SheetInfo;
+use crate::error::Error;
+use elektron_sexp::{Schema, SchemaElement, Symbol, TitleBlock};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref VARIABLE: Regex = Regex::new(r"\$\{([^}]+)\}").unwrap();
+}
+
+/// nested variables are resolved up to this depth.
+const MAX_DEPTH: usize = 8;
+
+/// Text variables like `${TITLE}` or `${R1:VALUE}`.
+///
+/// The variables are resolved from the symbol fields, the page and title block
+/// data and the project variables, in this order.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    variables: HashMap<String, String>,
+    fields: HashMap<String, HashMap<String, String>>,
+}
+
+impl Variables {
+    /// create the variables with the user defined project variables.
+    pub fn new(project: &HashMap<String, String>) -> Self {
+        Self {
+            variables: project.clone(),
+            fields: HashMap::new(),
+        }
+    }
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.variables.insert(key.to_string(), value.to_string());
+    }
+    /// collect the symbol fields of all pages for references like `${R1:VALUE}`.
+    pub fn symbols(&mut self, schema: &Schema) -> Result<(), Error> {
+        for page in 0..schema.pages() {
+            for element in schema.iter(page)? {
+                if let SchemaElement::Symbol(symbol) = element {
+                    let fields = symbol_fields(symbol);
+                    if let Some(reference) = fields.get("REFERENCE") {
+                        self.fields.insert(reference.clone(), fields);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// get a copy of the variables with the page and title block variables.
+    ///
+    /// The version is the generator and version of the file, it is empty when unknown.
+    pub fn page(
+        &self,
+        title_block: &Option<TitleBlock>,
+        sheet: &SheetInfo,
+        paper: &str,
+        version: Option<&str>,
+    ) -> Self {
+        let mut variables = self.clone();
+        variables.insert("PAPER", paper);
+        variables.insert("KICAD_VERSION", version.unwrap_or_default());
+        variables.insert("#", &sheet.number.to_string());
+        variables.insert("##", &sheet.count.to_string());
+        variables.insert("SHEETNAME", &sheet.name);
+        variables.insert("SHEETPATH", &sheet.path);
+        variables.insert("FILENAME", &sheet.filename);
+        if let Some(title_block) = title_block {
+            variables.insert("TITLE", &title_block.title);
+            variables.insert("ISSUE_DATE", &title_block.date);
+            variables.insert("REVISION", &title_block.rev);
+            variables.insert("COMPANY", &title_block.company);
+            for (key, comment) in &title_block.comment {
+                variables.insert(&format!("COMMENT{}", key), &comment.to_string());
+            }
+        }
+        variables
+    }
+    /// replace the variables in the text, unknown variables are kept.
+    pub fn resolve(&self, text: &str) -> String {
+        self.substitute(text, &HashMap::new())
+    }
+    /// replace the variables in a field text of the symbol.
+    pub fn resolve_symbol(&self, text: &str, symbol: &Symbol) -> String {
+        self.substitute(text, &symbol_fields(symbol))
+    }
+    fn substitute(&self, text: &str, fields: &HashMap<String, String>) -> String {
+        let mut result = text.to_string();
+        for _ in 0..MAX_DEPTH {
+            if !VARIABLE.is_match(&result) {
+                break;
+            }
+            let next = VARIABLE
+                .replace_all(&result, |caps: &regex::Captures| {
+                    self.lookup(&caps[1], fields)
+                        .unwrap_or_else(|| caps[0].to_string())
+                })
+                .to_string();
+            if next == result {
+                break;
+            }
+            result = next;
+        }
+        result
+    }
+    fn lookup(&self, name: &str, fields: &HashMap<String, String>) -> Option<String> {
+        if let Some(value) = fields.get(name) {
+            return Some(value.clone());
+        }
+        if let Some((reference, field)) = name.split_once(':') {
+            return self
+                .fields
+                .get(reference)
+                .and_then(|f| f.get(field).or_else(|| f.get(&field.to_uppercase())))
+                .cloned();
+        }
+        self.variables.get(name).cloned()
+    }
+}
+
+/// get the fields of the symbol, the names are also available in upper case.
+fn symbol_fields(symbol: &Symbol) -> HashMap<String, String> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    for property in &symbol.property {
+        fields.insert(property.key.clone(), property.value.clone());
+        fields.insert(property.key.to_uppercase(), property.value.clone());
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::Variables;
+    use crate::border::SheetInfo;
+    use elektron_sexp::Schema;
+
+    #[test]
+    fn project_variables() {
+        let mut project = HashMap::new();
+        project.insert(String::from("VERSION"), String::from("1.0"));
+        project.insert(String::from("NAME"), String::from("summe ${VERSION}"));
+        let variables = Variables::new(&project);
+        assert_eq!(
+            "summe 1.0 ${UNKNOWN}",
+            variables.resolve("${NAME} ${UNKNOWN}")
+        );
+    }
+    #[test]
+    fn page_variables() {
+        let doc = Schema::load("files/summe.kicad_sch").unwrap();
+        let sheet = SheetInfo {
+            number: 2,
+            count: 3,
+            ..Default::default()
+        };
+        let variables = Variables::default().page(
+            &doc.pages[0].title_block,
+            &sheet,
+            "A4",
+            Some("eeschema 20211123"),
+        );
+        assert_eq!(
+            "summe R02 2/3 A4 eeschema 20211123",
+            variables.resolve("${TITLE} ${REVISION} ${#}/${##} ${PAPER} ${KICAD_VERSION}")
+        );
+        let variables = Variables::default().page(&None, &sheet, "A4", None);
+        assert_eq!("", variables.resolve("${KICAD_VERSION}"));
+    }
+}
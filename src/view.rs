@@ -0,0 +1,149 @@
+use crate::error::Error;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref REFERENCE: Regex =
+        Regex::new(r"^([A-Za-z_#]+)(\d+)(?:\s*(?:\.\.|-)\s*(?:[A-Za-z_#]+)?(\d+))?$").unwrap();
+}
+
+/// The part of the page that is plotted.
+///
+/// The view is a rectangle in sheet mm or the bounding box of the symbols
+/// with the references, the padding is added around the view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    area: Option<((f64, f64), (f64, f64))>,
+    /// the reference prefix and the range of the numbers.
+    references: Vec<(String, u32, u32)>,
+    spec: String,
+    pub padding: f64,
+}
+
+impl View {
+    /// create the view of the rectangle in sheet mm.
+    pub fn area(start: (f64, f64), end: (f64, f64)) -> Self {
+        Self {
+            area: Some((
+                (start.0.min(end.0), start.1.min(end.1)),
+                (start.0.max(end.0), start.1.max(end.1)),
+            )),
+            references: Vec::new(),
+            spec: String::new(),
+            padding: 0.0,
+        }
+    }
+    /// create the view of the symbols, the references are a list like `U1, R3..R7`.
+    pub fn references(spec: &str) -> Result<Self, Error> {
+        let mut references = Vec::new();
+        for token in spec.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            let caps = REFERENCE.captures(token).ok_or_else(|| {
+                Error::Pattern(spec.to_string(), format!("invalid reference \"{}\"", token))
+            })?;
+            let start: u32 = caps[2].parse().unwrap_or_default();
+            let end: u32 = caps
+                .get(3)
+                .and_then(|end| end.as_str().parse().ok())
+                .unwrap_or(start);
+            references.push((caps[1].to_string(), start.min(end), start.max(end)));
+        }
+        if references.is_empty() {
+            return Err(Error::Pattern(
+                spec.to_string(),
+                String::from("no references"),
+            ));
+        }
+        Ok(Self {
+            area: None,
+            references,
+            spec: spec.to_string(),
+            padding: 2.54,
+        })
+    }
+    /// set the padding around the view in mm.
+    pub fn padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+    /// test if the symbol with the reference is in the view.
+    pub fn matches(&self, reference: &str) -> bool {
+        let split = reference
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(reference.len());
+        let (prefix, number) = reference.split_at(split);
+        match number.parse::<u32>() {
+            Ok(number) => self
+                .references
+                .iter()
+                .any(|(p, start, end)| p == prefix && number >= *start && number <= *end),
+            Err(_) => false,
+        }
+    }
+    /// the view uses the symbol bounds.
+    pub fn has_references(&self) -> bool {
+        !self.references.is_empty()
+    }
+    /// get the bounds of the view with the padding.
+    ///
+    /// The points are the outline of the symbols in the view, `None` is returned
+    /// when no symbol of the references is on the page.
+    pub fn bounds(&self, points: &[(f64, f64)]) -> Option<((f64, f64), (f64, f64))> {
+        let ((x0, y0), (x1, y1)) = if let Some(area) = self.area {
+            area
+        } else if points.is_empty() {
+            return None;
+        } else {
+            points.iter().fold(
+                (
+                    (f64::INFINITY, f64::INFINITY),
+                    (f64::NEG_INFINITY, f64::NEG_INFINITY),
+                ),
+                |((x0, y0), (x1, y1)), (x, y)| ((x0.min(*x), y0.min(*y)), (x1.max(*x), y1.max(*y))),
+            )
+        };
+        Some((
+            (x0 - self.padding, y0 - self.padding),
+            (x1 + self.padding, y1 + self.padding),
+        ))
+    }
+}
+
+impl std::fmt::Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(((x0, y0), (x1, y1))) = self.area {
+            write!(f, "{} {} {} {}", x0, y0, x1, y1)
+        } else {
+            write!(f, "{}", self.spec)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::View;
+
+    #[test]
+    fn references() {
+        let view = View::references("U1, R3..R7, C10-12").unwrap();
+        assert!(view.matches("U1"));
+        assert!(!view.matches("U2"));
+        assert!(view.matches("R3"));
+        assert!(view.matches("R7"));
+        assert!(!view.matches("R8"));
+        assert!(view.matches("C11"));
+        assert!(!view.matches("RV5"));
+        assert!(!view.matches("#PWR01"));
+        assert!(View::references("U1, 3").is_err());
+    }
+    #[test]
+    fn bounds() {
+        let view = View::references("R1").unwrap().padding(1.0);
+        assert_eq!(None, view.bounds(&[]));
+        assert_eq!(
+            Some(((0.0, 1.0), (11.0, 6.0))),
+            view.bounds(&[(1.0, 5.0), (10.0, 2.0)])
+        );
+        let view = View::area((20.0, 10.0), (0.0, 0.0));
+        assert_eq!(Some(((0.0, 0.0), (20.0, 10.0))), view.bounds(&[]));
+    }
+}
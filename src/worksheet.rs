@@ -0,0 +1,458 @@
+use crate::cairo_plotter::{Image, Line, LineCap, PlotItem, Rectangle, Text, BITMAP_PPI};
+use crate::error::{Error, Warning};
+use crate::sexp::Node;
+use crate::theme::Theme;
+use crate::variables::Variables;
+use ndarray::{arr1, arr2, Array1};
+
+/// The corner the coordinates of a drawing sheet item are relative to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Corner {
+    LeftTop,
+    LeftBottom,
+    RightBottom,
+    RightTop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Position {
+    x: f64,
+    y: f64,
+    corner: Corner,
+}
+
+impl Position {
+    fn from(node: Option<&Node>) -> Self {
+        let corner = if let Some(node) = node {
+            if node.has("ltcorner") {
+                Corner::LeftTop
+            } else if node.has("lbcorner") {
+                Corner::LeftBottom
+            } else if node.has("rtcorner") {
+                Corner::RightTop
+            } else {
+                Corner::RightBottom
+            }
+        } else {
+            Corner::RightBottom
+        };
+        Position {
+            x: node.and_then(|n| n.f64(0)).unwrap_or(0.0),
+            y: node.and_then(|n| n.f64(1)).unwrap_or(0.0),
+            corner,
+        }
+    }
+}
+
+/// Repeat settings and page options common to all items.
+#[derive(Debug, Clone, PartialEq)]
+struct Common {
+    repeat: usize,
+    incrx: f64,
+    incry: f64,
+    page1only: bool,
+    notonpage1: bool,
+}
+
+impl Common {
+    fn from(node: &Node) -> Self {
+        let option = node.get("option");
+        Common {
+            repeat: node.child_f64("repeat").unwrap_or(1.0).max(1.0) as usize,
+            incrx: node.child_f64("incrx").unwrap_or(0.0),
+            incry: node.child_f64("incry").unwrap_or(0.0),
+            page1only: option.map(|o| o.has("page1only")).unwrap_or(false),
+            notonpage1: option.map(|o| o.has("notonpage1")).unwrap_or(false),
+        }
+    }
+    fn on_page(&self, page: usize) -> bool {
+        !(self.page1only && page != 1 || self.notonpage1 && page == 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    Line {
+        start: Position,
+        end: Position,
+        width: Option<f64>,
+        common: Common,
+    },
+    Rect {
+        start: Position,
+        end: Position,
+        width: Option<f64>,
+        common: Common,
+    },
+    Text {
+        text: String,
+        pos: Position,
+        size: Option<f64>,
+        justify: Vec<String>,
+        rotate: f64,
+        incrlabel: i32,
+        common: Common,
+    },
+    Bitmap {
+        pos: Position,
+        scale: f64,
+        data: Vec<u8>,
+        common: Common,
+    },
+    Unsupported(String),
+}
+
+/// A KiCad drawing sheet loaded from a `.kicad_wks` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Worksheet {
+    text_size: f64,
+    line_width: f64,
+    margins: (f64, f64, f64, f64),
+    items: Vec<Item>,
+}
+
+impl Worksheet {
+    pub fn load(filename: &str) -> Result<Self, Error> {
+        Self::from(std::fs::read_to_string(filename)?)
+    }
+    pub fn from(content: String) -> Result<Self, Error> {
+        let root = Node::parse(content).ok_or(Error::ParseError)?;
+        if root.name != "kicad_wks" && root.name != "page_layout" {
+            return Err(Error::ParseError);
+        }
+        let setup = root.get("setup");
+        let setup_f64 =
+            |name: &str, default: f64| setup.and_then(|s| s.child_f64(name)).unwrap_or(default);
+        let mut items = Vec::new();
+        for node in &root.nodes {
+            match node.name.as_str() {
+                // the header and the setup are not drawn.
+                "setup" | "version" | "generator" => {}
+                "line" => items.push(Item::Line {
+                    start: Position::from(node.get("start")),
+                    end: Position::from(node.get("end")),
+                    width: node.child_f64("linewidth"),
+                    common: Common::from(node),
+                }),
+                "rect" => items.push(Item::Rect {
+                    start: Position::from(node.get("start")),
+                    end: Position::from(node.get("end")),
+                    width: node.child_f64("linewidth"),
+                    common: Common::from(node),
+                }),
+                "tbtext" => {
+                    let font = node.get("font");
+                    items.push(Item::Text {
+                        text: node.value(0).unwrap_or_default().to_string(),
+                        pos: Position::from(node.get("pos")),
+                        size: font.and_then(|f| f.get("size")).and_then(|s| s.f64(0)),
+                        justify: node
+                            .get("justify")
+                            .map(|j| j.values.clone())
+                            .unwrap_or_default(),
+                        rotate: node.child_f64("rotate").unwrap_or(0.0),
+                        incrlabel: node.child_f64("incrlabel").unwrap_or(1.0) as i32,
+                        common: Common::from(node),
+                    })
+                }
+                "bitmap" => {
+                    let mut data: Vec<u8> = Vec::new();
+                    if let Some(png) = node.get("pngdata") {
+                        for line in png.all("data") {
+                            for value in &line.values {
+                                for byte in value.split_whitespace() {
+                                    data.push(
+                                        u8::from_str_radix(byte, 16)
+                                            .map_err(|_| Error::ParseError)?,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    items.push(Item::Bitmap {
+                        pos: Position::from(node.get("pos")),
+                        scale: node.child_f64("scale").unwrap_or(1.0),
+                        data,
+                        common: Common::from(node),
+                    })
+                }
+                name => items.push(Item::Unsupported(name.to_string())),
+            }
+        }
+        Ok(Worksheet {
+            text_size: setup
+                .and_then(|s| s.get("textsize"))
+                .and_then(|s| s.f64(0))
+                .unwrap_or(1.5),
+            line_width: setup_f64("linewidth", 0.15),
+            margins: (
+                setup_f64("left_margin", 10.0),
+                setup_f64("top_margin", 10.0),
+                setup_f64("right_margin", 10.0),
+                setup_f64("bottom_margin", 10.0),
+            ),
+            items,
+        })
+    }
+
+    /// get the page position of the item position for the repetition.
+    fn position(
+        &self,
+        pos: &Position,
+        common: &Common,
+        index: usize,
+        paper_size: (f64, f64),
+    ) -> Option<Array1<f64>> {
+        let left = self.margins.0;
+        let top = self.margins.1;
+        let right = paper_size.0 - self.margins.2;
+        let bottom = paper_size.1 - self.margins.3;
+        let x = pos.x + common.incrx * index as f64;
+        let y = pos.y + common.incry * index as f64;
+        let (x, y) = match pos.corner {
+            Corner::LeftTop => (left + x, top + y),
+            Corner::LeftBottom => (left + x, bottom - y),
+            Corner::RightBottom => (right - x, bottom - y),
+            Corner::RightTop => (right - x, top + y),
+        };
+        // repeated items are only drawn inside the page margins.
+        if index > 0
+            && (x < left - 0.001 || x > right + 0.001 || y < top - 0.001 || y > bottom + 0.001)
+        {
+            None
+        } else {
+            Some(arr1(&[x, y]))
+        }
+    }
+
+    /// plot the drawing sheet, the variables are used for the text items.
+    pub fn plot(
+        &self,
+        paper_size: (f64, f64),
+        variables: &Variables,
+        page: usize,
+        theme: &Theme,
+        warnings: &mut Vec<Warning>,
+    ) -> Vec<PlotItem> {
+        let stroke = theme.stroke_or_default("border_stroke", warnings);
+        let effects = theme.effects_or_default("border_effects", warnings);
+        let mut plotter: Vec<PlotItem> = Vec::new();
+        for item in &self.items {
+            match item {
+                Item::Line {
+                    start,
+                    end,
+                    width,
+                    common,
+                } if common.on_page(page) => {
+                    for i in 0..common.repeat {
+                        let (Some(start), Some(end)) = (
+                            self.position(start, common, i, paper_size),
+                            self.position(end, common, i, paper_size),
+                        ) else {
+                            break;
+                        };
+                        plotter.push(PlotItem::Line(
+                            99,
+                            Line::new(
+                                arr2(&[[start[0], start[1]], [end[0], end[1]]]),
+                                width.unwrap_or(self.line_width),
+                                stroke.linetype.clone(),
+                                LineCap::Butt,
+                                stroke.color,
+                            ),
+                        ));
+                    }
+                }
+                Item::Rect {
+                    start,
+                    end,
+                    width,
+                    common,
+                } if common.on_page(page) => {
+                    for i in 0..common.repeat {
+                        let (Some(start), Some(end)) = (
+                            self.position(start, common, i, paper_size),
+                            self.position(end, common, i, paper_size),
+                        ) else {
+                            break;
+                        };
+                        plotter.push(PlotItem::Rectangle(
+                            99,
+                            Rectangle::new(
+                                arr2(&[[start[0], start[1]], [end[0], end[1]]]),
+                                stroke.color,
+                                width.unwrap_or(self.line_width),
+                                stroke.linetype.clone(),
+                                None,
+                            ),
+                        ));
+                    }
+                }
+                Item::Text {
+                    text,
+                    pos,
+                    size,
+                    justify,
+                    rotate,
+                    incrlabel,
+                    common,
+                } if common.on_page(page) => {
+                    let text = variables.resolve(text);
+                    // the default justification is left, center is the default of the plotter.
+                    let mut align: Vec<String> =
+                        justify.iter().filter(|j| *j != "center").cloned().collect();
+                    if !justify.iter().any(|j| j == "center" || j == "right") {
+                        align.push(String::from("left"));
+                    }
+                    for i in 0..common.repeat {
+                        let Some(pos) = self.position(pos, common, i, paper_size) else {
+                            break;
+                        };
+                        plotter.push(PlotItem::Text(
+                            99,
+                            Text::new(
+                                pos,
+                                *rotate,
+                                increment_label(&text, i as i32 * incrlabel),
+                                effects.color,
+                                size.unwrap_or(self.text_size),
+                                effects.font.as_str(),
+                                align.clone(),
+                                false,
+                            ),
+                        ));
+                    }
+                }
+                Item::Bitmap {
+                    pos,
+                    scale,
+                    data,
+                    common,
+                } if common.on_page(page) => {
+                    for i in 0..common.repeat {
+                        let Some(pos) = self.position(pos, common, i, paper_size) else {
+                            break;
+                        };
+                        plotter.push(PlotItem::Image(
+                            99,
+                            Image::new(pos, 25.4 / BITMAP_PPI * scale, data.clone()),
+                        ));
+                    }
+                }
+                Item::Unsupported(name) => warnings.push(Warning::UnsupportedElement(format!(
+                    "drawing sheet {}",
+                    name
+                ))),
+                _ => {}
+            }
+        }
+        plotter
+    }
+}
+
+/// increment the last character of a repeated text, numbers and letters are supported.
+fn increment_label(text: &str, increment: i32) -> String {
+    if increment == 0 || text.is_empty() {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    let last = chars.pop().unwrap_or_default();
+    let mut label: String = chars.into_iter().collect();
+    if let Some(digit) = last.to_digit(10) {
+        label.push_str(&(digit as i32 + increment).to_string());
+    } else if let Some(c) = char::from_u32((last as i32 + increment) as u32) {
+        label.push(c);
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{increment_label, Worksheet};
+    use crate::cairo_plotter::PlotItem;
+    use crate::error::Warning;
+    use crate::theme::Theme;
+    use crate::variables::Variables;
+
+    #[test]
+    fn labels() {
+        assert_eq!("1", increment_label("1", 0));
+        assert_eq!("12", increment_label("1", 11));
+        assert_eq!("C", increment_label("A", 2));
+    }
+    #[test]
+    fn repeat_inside_margin() {
+        let worksheet = Worksheet::from(String::from(
+            r#"(kicad_wks (version 20210606) (generator pl_editor)
+              (setup (textsize 1.5 1.5)(linewidth 0.15)(textlinewidth 0.15)
+                (left_margin 10)(right_margin 10)(top_margin 10)(bottom_margin 10))
+              (line (name "") (start 50 2 ltcorner) (end 50 0 ltcorner) (repeat 30) (incrx 50))
+              (tbtext "1" (name "") (pos 25 1 ltcorner) (font (size 1.3 1.3)) (repeat 100) (incrx 50))
+            )"#,
+        ))
+        .unwrap();
+        let mut warnings = Vec::new();
+        let items = worksheet.plot(
+            (297.0, 210.0),
+            &Variables::default(),
+            1,
+            &Theme::kicad_2000(),
+            &mut warnings,
+        );
+        let lines = items
+            .iter()
+            .filter(|i| matches!(i, PlotItem::Line(..)))
+            .count();
+        let texts = items
+            .iter()
+            .filter(|i| matches!(i, PlotItem::Text(..)))
+            .count();
+        assert_eq!(5, lines);
+        assert_eq!(6, texts);
+        assert!(warnings.is_empty());
+    }
+    #[test]
+    fn bitmap() {
+        let worksheet = Worksheet::from(String::from(
+            r#"(kicad_wks (version 20210606) (generator pl_editor)
+              (bitmap (name "") (pos 20 20) (scale 0.5)
+                (pngdata (data "89 50 4E 47 0D 0A 1A 0A")))
+            )"#,
+        ))
+        .unwrap();
+        let mut warnings = Vec::new();
+        let items = worksheet.plot(
+            (297.0, 210.0),
+            &Variables::default(),
+            1,
+            &Theme::kicad_2000(),
+            &mut warnings,
+        );
+        assert!(matches!(&items[..], [PlotItem::Image(_, image)] if image.data.len() == 8));
+        assert!(warnings.is_empty());
+    }
+    #[test]
+    fn unsupported_items() {
+        let worksheet = Worksheet::from(String::from(
+            r#"(kicad_wks (version 20210606) (generator pl_editor)
+              (polygon (name "") (pos 10 10) (pts (xy 0 0) (xy 1 1)))
+            )"#,
+        ))
+        .unwrap();
+        let mut warnings = Vec::new();
+        worksheet.plot(
+            (297.0, 210.0),
+            &Variables::default(),
+            1,
+            &Theme::kicad_2000(),
+            &mut warnings,
+        );
+        assert_eq!(
+            vec![Warning::UnsupportedElement(String::from(
+                "drawing sheet polygon"
+            ))],
+            warnings
+        );
+    }
+}